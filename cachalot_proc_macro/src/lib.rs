@@ -34,6 +34,50 @@ impl FileSize {
 struct StoreArgs {
     #[darling(default)]
     root: Option<String>,
+    #[darling(default)]
+    end: Option<String>,
+    #[darling(default)]
+    page_size: Option<u128>,
+    #[darling(default)]
+    prefetch: Option<usize>,
+    #[darling(default)]
+    max_source_range: Option<u128>,
+    #[darling(default)]
+    max_concurrent: Option<usize>,
+    #[darling(default)]
+    max_calls: Option<usize>,
+    #[darling(default)]
+    window_ms: Option<u64>,
+    #[darling(default)]
+    retries: Option<usize>,
+    #[darling(default)]
+    backoff_ms: Option<u64>,
+    #[darling(default)]
+    retry_if: Option<String>,
+    #[darling(default)]
+    breaker: Option<usize>,
+    #[darling(default)]
+    cooldown_ms: Option<u64>,
+    #[darling(default)]
+    breaker_per_key: bool,
+    #[darling(default)]
+    negative_cache_ms: Option<u64>,
+    #[darling(default)]
+    call_timeout_ms: Option<u64>,
+    #[darling(default)]
+    item_timeout_ms: Option<u64>,
+    #[darling(default)]
+    write_behind: Option<String>,
+    #[darling(default)]
+    detach: bool,
+    #[darling(default)]
+    summary: Option<String>,
+    #[darling(default)]
+    codec: bool,
+    #[darling(default)]
+    nullable: bool,
+    #[darling(default)]
+    sparse: bool,
     #[darling(default, map=FileSize::Bytes)]
     bytes: FileSize,
     #[darling(default, map=FileSize::Kbs)]
@@ -162,18 +206,185 @@ pub fn cachalot(args: TokenStream, input: TokenStream) -> TokenStream {
         let range_pat = args_pats.pop().unwrap();
         let key_pats = args_pats;
 
+        let page_size = store_args.page_size.unwrap_or(1024);
+
+        let config_prefetch = store_args
+            .prefetch
+            .map(|prefetch| quote!(config.prefetch = #prefetch;));
+
+        let config_max_source_range = store_args
+            .max_source_range
+            .map(|max| quote!(config.max_source_range = Some(#max);));
+
+        let config_governor = {
+            let max_concurrent = store_args
+                .max_concurrent
+                .map(|calls| quote!(.max_concurrent(#calls)));
+
+            let window_ms = store_args.window_ms.unwrap_or(1000);
+            let max_calls = store_args.max_calls.map(
+                |calls| quote!(.max_calls(#calls, std::time::Duration::from_millis(#window_ms))),
+            );
+
+            (max_concurrent.is_some() || max_calls.is_some()).then(|| {
+                quote! {
+                    static GOVERNOR: std::sync::LazyLock<std::sync::Arc<cachalot::Governor>> =
+                        std::sync::LazyLock::new(|| {
+                            std::sync::Arc::new(cachalot::Governor::new() #max_concurrent #max_calls)
+                        });
+
+                    config.governor = Some(std::sync::Arc::clone(&GOVERNOR));
+                }
+            })
+        };
+
+        let config_retry = store_args.retries.map(|retries| {
+            let backoff = store_args.backoff_ms.map(|ms| {
+                quote!(.backoff(
+                    std::time::Duration::from_millis(#ms),
+                    cachalot::RetryPolicy::<()>::DEFAULT_MAX_DELAY,
+                ))
+            });
+
+            let retry_if = store_args.retry_if.as_ref().map(|retry_if| {
+                let retry_if = match syn::parse_str::<syn::Path>(retry_if) {
+                    Ok(retry_if) => retry_if,
+                    Err(e) => abort!(e.span(), "can't parse cachalot `retry_if` function: {}", e),
+                };
+
+                quote!(.retry_if(#retry_if))
+            });
+
+            quote!(config.retry(
+                cachalot::RetryPolicy::for_source(&#ident::<#(#generic_type_params),*>, #retries) #backoff #retry_if
+            );)
+        });
+
+        let config_breaker = store_args.breaker.map(|failures| {
+            let cooldown_ms = store_args.cooldown_ms.unwrap_or(30_000);
+            let per_key = store_args.breaker_per_key.then(|| quote!(.per_key()));
+
+            quote! {
+                static BREAKER: std::sync::LazyLock<std::sync::Arc<cachalot::CircuitBreaker>> =
+                    std::sync::LazyLock::new(|| {
+                        std::sync::Arc::new(
+                            cachalot::CircuitBreaker::new(
+                                #failures,
+                                std::time::Duration::from_millis(#cooldown_ms),
+                            ) #per_key,
+                        )
+                    });
+
+                config.breaker = Some(std::sync::Arc::clone(&BREAKER));
+            }
+        });
+
+        let config_negative_cache = store_args.negative_cache_ms.map(|ms| {
+            quote! {
+                static NEGATIVE_CACHE: std::sync::LazyLock<std::sync::Arc<cachalot::NegativeCache>> =
+                    std::sync::LazyLock::new(|| {
+                        std::sync::Arc::new(cachalot::NegativeCache::new(
+                            std::time::Duration::from_millis(#ms),
+                        ))
+                    });
+
+                config.negative_cache = Some(std::sync::Arc::clone(&NEGATIVE_CACHE));
+            }
+        });
+
+        let config_call_timeout = store_args.call_timeout_ms.map(
+            |ms| quote!(config.call_timeout = Some(std::time::Duration::from_millis(#ms));),
+        );
+
+        let config_item_timeout = store_args.item_timeout_ms.map(
+            |ms| quote!(config.item_timeout = Some(std::time::Duration::from_millis(#ms));),
+        );
+
+        let config_write_behind = store_args.write_behind.map(|write_behind| {
+            let write_behind = match syn::parse_str::<syn::Path>(&write_behind) {
+                Ok(write_behind) => write_behind,
+                Err(e) => abort!(
+                    e.span(),
+                    "can't parse cachalot `write_behind` function: {}",
+                    e
+                ),
+            };
+
+            quote!(config.write_behind = Some(#write_behind());)
+        });
+
+        let config_summary = store_args.summary.map(|summary| {
+            let summary = match syn::parse_str::<syn::Path>(&summary) {
+                Ok(summary) => summary,
+                Err(e) => abort!(e.span(), "can't parse cachalot `summary` type: {}", e),
+            };
+
+            quote!(config.summarize::<#summary>();)
+        });
+
+        let config_end = store_args.end.map(|end| {
+            let end = match syn::parse_str::<syn::Path>(&end) {
+                Ok(end) => end,
+                Err(e) => abort!(e.span(), "can't parse cachalot `end` function: {}", e),
+            };
+
+            // an end before the page space leaves no data
+            quote!(config.end = Some(cachalot::IndexDomain::to_idx(#end(#(#key_pats),*)).unwrap_or(0));)
+        });
+
+        let format = match (store_args.codec, store_args.nullable, store_args.sparse) {
+            (false, false, false) => quote!(cachalot::Plain),
+            (true, false, false) => quote!(cachalot::Encoded),
+            (false, true, false) => quote!(cachalot::Nullable),
+            (false, false, true) => quote!(cachalot::Sparse),
+            _ => abort!(
+                ident,
+                "cachalot `codec`, `nullable` and `sparse` can't be combined"
+            ),
+        };
+
+        // The breaker and the negative cache fail loads without calling the source,
+        // so these loads yield the failures as the source error instead of panicking.
+        let fallible = store_args.breaker.is_some() || store_args.negative_cache_ms.is_some();
+
+        let load = match (store_args.detach, fallible) {
+            (false, false) => quote!(#ident.load((#(#key_pats),*), #range_pat, &config)),
+            (true, false) => quote!(#ident.load_detached((#(#key_pats),*), #range_pat, &config)),
+            (false, true) => {
+                quote!(cachalot::TryStore::load_into(&#ident, (#(#key_pats),*), #range_pat, &config))
+            }
+            (true, true) => quote!(cachalot::TryStore::load_detached_into(
+                &#ident,
+                (#(#key_pats),*),
+                #range_pat,
+                &config
+            )),
+        };
+
         quote! {
             #vis #asyncness #unsafety fn #ident <#generic_params> (#inputs) #output #where_clause {
                 use cachalot::{Store, TryStore};
 
                 #inner_source
 
-                let mut config = #ident::<#(#generic_type_params),*>.config::<1024>();
+                let mut config: cachalot::TypedConfig<_, #page_size, #format> =
+                    #ident::<#(#generic_type_params),*>.config::<#page_size>();
                 #config_root
-
-                #ident.load((#(#key_pats),*), #range_pat, &config).await
+                #config_end
+                #config_prefetch
+                #config_max_source_range
+                #config_governor
+                #config_retry
+                #config_breaker
+                #config_negative_cache
+                #config_call_timeout
+                #config_item_timeout
+                #config_write_behind
+                #config_summary
+
+                #load.await
             }
         }
     }
     .into()
-}
\ No newline at end of file
+}
@@ -0,0 +1,285 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::source::TrySource;
+use crate::{Idx, StoreError};
+
+/// Stops calling a failing source: after `failures` consecutive failed calls,
+/// the next calls fail right away until `cooldown` has passed.
+///
+/// The failures are counted for the whole function, or for each key with [`CircuitBreaker::per_key`].
+pub struct CircuitBreaker {
+    failures: usize,
+    cooldown: Duration,
+    per_key: bool,
+    states: Mutex<HashMap<PathBuf, BreakerState>>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive: usize,
+    opened: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failures: usize, cooldown: Duration) -> Self {
+        Self {
+            failures: failures.max(1),
+            cooldown,
+            per_key: false,
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn per_key(mut self) -> Self {
+        self.per_key = true;
+        self
+    }
+
+    fn key(&self, dir: &Path) -> PathBuf {
+        match self.per_key {
+            true => dir.to_path_buf(),
+            false => PathBuf::new(),
+        }
+    }
+
+    /// The consecutive failures, when the breaker is open for `dir`.
+    pub(crate) fn open(&self, dir: &Path) -> Option<usize> {
+        let states = self.states.lock().unwrap();
+        let state = states.get(&self.key(dir))?;
+
+        state
+            .opened
+            .is_some_and(|opened| opened.elapsed() < self.cooldown)
+            .then_some(state.consecutive)
+    }
+
+    pub(crate) fn record(&self, dir: &Path, success: bool) {
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(self.key(dir)).or_default();
+
+        if success {
+            *state = BreakerState::default();
+        } else {
+            state.consecutive += 1;
+
+            if state.consecutive >= self.failures {
+                state.opened = Some(Instant::now());
+            }
+        }
+    }
+}
+
+impl Hash for CircuitBreaker {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.failures.hash(state);
+        self.cooldown.hash(state);
+        self.per_key.hash(state);
+    }
+}
+
+/// A copy of a remembered failure, when it is a `StoreError` of the error type it was made for.
+type Replay = fn(&dyn Any) -> Option<Box<dyn Any + Send + Sync>>;
+
+/// Remembers the failed ranges for `ttl`, so that loading them again fails right away.
+///
+/// Made [for a source](NegativeCache::for_source), it also remembers the error each range failed with,
+/// and fails the loads with that error again, rather than with a [`StoreError::RecentFailure`].
+pub struct NegativeCache {
+    ttl: Duration,
+    replay: Option<Replay>,
+    failures: Mutex<Vec<Failure>>,
+}
+
+struct Failure {
+    dir: PathBuf,
+    first: Idx,
+    last: Idx,
+    failed: Instant,
+    cause: Option<Box<dyn Any + Send + Sync>>,
+}
+
+impl NegativeCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            replay: None,
+            failures: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// A cache replaying the errors of `source`, which it copies.
+    pub fn for_source<S, R, K, V>(_source: &S, ttl: Duration) -> Self
+    where
+        S: TrySource<R, K, V>,
+        S::Error: Clone,
+    {
+        Self {
+            replay: Some(replay::<S::Error>),
+            ..Self::new(ttl)
+        }
+    }
+
+    /// The remembered failed range of `dir` overlapping `start..=end`, if any, with a copy of its error
+    /// when the cache replays the errors of the type `E`.
+    pub(crate) fn get<E: 'static>(
+        &self,
+        dir: &Path,
+        start: Idx,
+        end: Idx,
+    ) -> Option<(Idx, Idx, Option<StoreError<E>>)> {
+        let mut failures = self.failures.lock().unwrap();
+        failures.retain(|failure| failure.failed.elapsed() < self.ttl);
+
+        let failure = failures
+            .iter()
+            .find(|failure| failure.dir == dir && failure.first <= end && start <= failure.last)?;
+        let cause = self
+            .replay
+            .zip(failure.cause.as_deref())
+            .and_then(|(replay, cause)| replay(cause))
+            .and_then(|cause| cause.downcast().ok())
+            .map(|cause| *cause);
+
+        Some((failure.first, failure.last, cause))
+    }
+
+    pub(crate) fn insert<E: 'static>(&self, dir: &Path, start: Idx, end: Idx, err: &StoreError<E>) {
+        let failure = Failure {
+            dir: dir.to_path_buf(),
+            first: start,
+            last: end,
+            failed: Instant::now(),
+            cause: self.replay.and_then(|replay| replay(err)),
+        };
+
+        self.failures.lock().unwrap().push(failure);
+    }
+}
+
+/// Copies a failure of the source call, which are the only ones remembered.
+fn replay<E: Clone + Send + Sync + 'static>(
+    failure: &dyn Any,
+) -> Option<Box<dyn Any + Send + Sync>> {
+    let failure = match failure.downcast_ref::<StoreError<E>>()? {
+        StoreError::External(err) => StoreError::External(err.clone()),
+        StoreError::CallTimeout(timeout) => StoreError::CallTimeout(*timeout),
+        StoreError::ItemTimeout(timeout) => StoreError::ItemTimeout(*timeout),
+        _ => return None,
+    };
+
+    Some(Box::new(failure))
+}
+
+impl Hash for NegativeCache {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ttl.hash(state);
+        self.replay.is_some().hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        let (a, b) = (Path::new("a"), Path::new("b"));
+
+        breaker.record(a, false);
+        assert_eq!(breaker.open(a), None);
+
+        breaker.record(b, false);
+        assert_eq!(breaker.open(a), Some(2));
+
+        breaker.record(a, true);
+        assert_eq!(breaker.open(b), None);
+
+        let breaker = CircuitBreaker::new(1, Duration::ZERO).per_key();
+
+        breaker.record(a, false);
+        assert_eq!(breaker.open(a), None);
+    }
+
+    #[test]
+    fn test_per_key_breaker() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60)).per_key();
+        let (a, b) = (Path::new("a"), Path::new("b"));
+
+        breaker.record(a, false);
+
+        assert_eq!(breaker.open(a), Some(1));
+        assert_eq!(breaker.open(b), None);
+    }
+
+    #[test]
+    fn test_negative_cache() {
+        fn get(cache: &NegativeCache, dir: &Path, start: Idx, end: Idx) -> Option<(Idx, Idx)> {
+            cache
+                .get::<()>(dir, start, end)
+                .map(|(first, last, _)| (first, last))
+        }
+
+        let cache = NegativeCache::new(Duration::from_secs(60));
+        let dir = Path::new("a");
+
+        cache.insert(dir, 100, 199, &StoreError::External(()));
+
+        assert_eq!(get(&cache, dir, 0, 99), None);
+        assert_eq!(get(&cache, dir, 150, 300), Some((100, 199)));
+        assert_eq!(get(&cache, Path::new("b"), 150, 300), None);
+
+        let cache = NegativeCache::new(Duration::ZERO);
+        cache.insert(dir, 100, 199, &StoreError::External(()));
+
+        assert_eq!(get(&cache, dir, 150, 300), None);
+    }
+
+    #[test]
+    fn test_negative_cache_replay() {
+        use std::ops::Range;
+
+        use futures::stream::{self, BoxStream, StreamExt};
+
+        async fn source(_k: (), _r: Range<Idx>) -> BoxStream<'static, Result<Idx, &'static str>> {
+            stream::empty().boxed()
+        }
+
+        let dir = Path::new("a");
+
+        let cache = NegativeCache::new(Duration::from_secs(60));
+        cache.insert(dir, 100, 199, &StoreError::External("down"));
+
+        assert!(matches!(
+            cache.get::<&str>(dir, 150, 300),
+            Some((100, 199, None))
+        ));
+
+        let cache = NegativeCache::for_source(&source, Duration::from_secs(60));
+        cache.insert(dir, 100, 199, &StoreError::External("down"));
+        cache.insert(
+            dir,
+            200,
+            299,
+            &StoreError::<&str>::CallTimeout(Duration::ZERO),
+        );
+
+        assert!(matches!(
+            cache.get::<&str>(dir, 150, 150),
+            Some((100, 199, Some(StoreError::External("down"))))
+        ));
+        assert!(matches!(
+            cache.get::<&str>(dir, 250, 250),
+            Some((200, 299, Some(StoreError::CallTimeout(_))))
+        ));
+        assert!(matches!(
+            cache.get::<u32>(dir, 150, 150),
+            Some((100, 199, None))
+        ));
+    }
+}
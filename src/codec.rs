@@ -0,0 +1,69 @@
+use bytemuck::{AnyBitPattern, NoUninit};
+
+pub trait PageCodec: Sized {
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+impl PageCodec for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl<T> PageCodec for Vec<T>
+where
+    T: NoUninit + AnyBitPattern,
+{
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(bytemuck::cast_slice(&self[..]));
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        use std::mem::size_of;
+
+        let size = size_of::<T>();
+
+        if size == 0 || !bytes.len().is_multiple_of(size) {
+            return None;
+        }
+
+        let mut items = vec![T::zeroed(); bytes.len() / size];
+        bytemuck::cast_slice_mut(&mut items[..]).copy_from_slice(bytes);
+
+        Some(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<V: PageCodec>(value: &V) -> Option<V> {
+        let mut buf = vec![];
+        value.encode(&mut buf);
+
+        V::decode(&buf)
+    }
+
+    #[test]
+    fn test_string() {
+        assert_eq!(round_trip(&String::new()), Some(String::new()));
+        assert_eq!(round_trip(&"cachalot".to_string()), Some("cachalot".into()));
+
+        assert_eq!(String::decode(&[0xff, 0xfe]), None);
+    }
+
+    #[test]
+    fn test_vec() {
+        assert_eq!(round_trip(&Vec::<u64>::new()), Some(vec![]));
+        assert_eq!(round_trip(&vec![1u64, 2, 3]), Some(vec![1, 2, 3]));
+
+        assert_eq!(Vec::<u32>::decode(&[0, 1, 2]), None);
+    }
+}
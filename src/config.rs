@@ -1,39 +1,126 @@
 use std::borrow::Cow;
 use std::marker::PhantomData;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
 use derive_more::{Deref, DerefMut};
 
-use crate::Idx;
+use crate::retry::AnyRetryPolicy;
+use crate::{
+    CancelToken, CircuitBreaker, Governor, Idx, NegativeCache, PageSummary, Plain, Pyramid,
+    RetryPolicy, Summary, WriteBehind,
+};
 
-#[derive(Hash)]
+#[derive(Clone, Hash)]
 pub struct Config<const PAGE_SIZE: Idx> {
     pub root: Cow<'static, Path>,
+    /// Exclusive end of the data: open ranges are loaded up to it, and a short
+    /// last page is taken as the end of the data rather than a source error.
+    pub end: Option<Idx>,
+    /// How many missing pages a page source may be fetching at once.
+    pub parallelism: usize,
+    /// How many of the next uncached runs get their source call started while the current one
+    /// is consumed, at most `parallelism` of them.
+    pub prefetch: usize,
+    /// Most items a single source call is asked for: longer ranges are split into page-aligned
+    /// calls of at least one page, each made once the previous one is consumed.
+    pub max_source_range: Option<Idx>,
+    /// Limits the source calls, shared by every config holding the same governor.
+    pub governor: Option<Arc<Governor>>,
+    /// Retries the failed calls of fallible sources, set through [`Config::retry`].
+    pub(crate) retry: Option<AnyRetryPolicy>,
+    /// Fails the loads right away while the source keeps failing.
+    pub breaker: Option<Arc<CircuitBreaker>>,
+    /// Fails the loads of the recently failed ranges right away.
+    pub negative_cache: Option<Arc<NegativeCache>>,
+    /// Longest wait for a source call to return its stream.
+    pub call_timeout: Option<Duration>,
+    /// Longest wait for each item of a source stream.
+    pub item_timeout: Option<Duration>,
+    /// Stops the load once cancelled, set only on the copy made for a cancellable load.
+    pub(crate) cancel: Option<CancelToken>,
+    /// Writes the fetched pages in the background instead of before yielding them.
+    pub write_behind: Option<Arc<WriteBehind>>,
+    /// Summarizes the full pages as they are cached, so that aggregates only scan the partial ones.
+    pub summary: Option<PageSummary>,
+    /// Reduces each level of the pyramid into the level above, for `load_level`.
+    pub pyramid: Option<Pyramid>,
 }
 
 impl<const PAGE_SIZE: Idx> Config<PAGE_SIZE> {
     pub const DEFAULT_ROOT: &'static str = ".cachalot";
+    pub const DEFAULT_PARALLELISM: usize = 8;
+    /// Most pages a reverse load fills at once when `max_source_range` is not set.
+    pub const DEFAULT_REV_RUN: usize = 8;
 
     pub fn new() -> Self {
         Config {
             root: Path::new(Self::DEFAULT_ROOT).into(),
+            end: None,
+            parallelism: Self::DEFAULT_PARALLELISM,
+            prefetch: 0,
+            max_source_range: None,
+            governor: None,
+            retry: None,
+            breaker: None,
+            negative_cache: None,
+            call_timeout: None,
+            item_timeout: None,
+            cancel: None,
+            write_behind: None,
+            summary: None,
+            pyramid: None,
         }
     }
+
+    /// Retries the failed calls of a fallible source whose error is `E`.
+    pub fn retry<E: Send + Sync + 'static>(&mut self, policy: RetryPolicy<E>) {
+        self.retry = Some(AnyRetryPolicy::new(policy));
+    }
 }
 
+/// A config for the items `V`, stored in the pages as the format `F` says.
 #[derive(Deref, DerefMut)]
-pub struct TypedConfig<V, const PAGE_SIZE: Idx> {
+pub struct TypedConfig<V, const PAGE_SIZE: Idx, F = Plain> {
     #[deref]
     #[deref_mut]
     pub config: Config<PAGE_SIZE>,
-    _type: PhantomData<V>,
+    _type: PhantomData<(V, F)>,
 }
 
-impl<V, const PAGE_SIZE: Idx> TypedConfig<V, PAGE_SIZE> {
+impl<V, const PAGE_SIZE: Idx, F> TypedConfig<V, PAGE_SIZE, F> {
     pub fn new() -> Self {
         Self {
             config: Config::new(),
             _type: PhantomData,
         }
     }
+
+    /// A copy of the config for a single load, which `cancel` stops.
+    pub(crate) fn cancelled_by(&self, cancel: &CancelToken) -> Self {
+        Self {
+            config: Config {
+                cancel: Some(cancel.clone()),
+                ..self.config.clone()
+            },
+            _type: PhantomData,
+        }
+    }
+
+    /// Summarizes the full pages as `S` when they are cached.
+    pub fn summarize<S: Summary<V>>(&mut self)
+    where
+        V: 'static,
+    {
+        self.config.summary = Some(PageSummary::new::<V, S>());
+    }
+
+    /// Builds each level of the pyramid by reducing `PAGE_SIZE` values of the level below into one.
+    pub fn reduce_levels(&mut self, reduce: impl Fn(&[V]) -> V + Send + Sync + 'static)
+    where
+        V: 'static,
+    {
+        self.config.pyramid = Some(Pyramid::new(reduce));
+    }
 }
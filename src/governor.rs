@@ -0,0 +1,152 @@
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{Future, Stream, StreamExt};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::{sleep_until, Instant};
+
+/// Limits the source calls of a function, across all of its keys:
+/// how many may run at once, and how many may start within a time window.
+///
+/// A call runs until its stream is consumed or dropped.
+/// A call made ahead of its turn, for a prefetched run, runs only while the source answers it,
+/// and again once its stream is read.
+pub struct Governor {
+    max_concurrent: Option<usize>,
+    max_calls: Option<(usize, Duration)>,
+    permits: Option<Arc<Semaphore>>,
+    calls: Mutex<VecDeque<Instant>>,
+}
+
+impl Governor {
+    pub fn new() -> Self {
+        Self {
+            max_concurrent: None,
+            max_calls: None,
+            permits: None,
+            calls: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn max_concurrent(mut self, calls: usize) -> Self {
+        self.max_concurrent = Some(calls);
+        self.permits = Some(Arc::new(Semaphore::new(calls.max(1))));
+        self
+    }
+
+    pub fn max_calls(mut self, calls: usize, window: Duration) -> Self {
+        self.max_calls = Some((calls.max(1), window));
+        self
+    }
+
+    /// Waits until a call is allowed, returning the permit it holds while running.
+    pub(crate) async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        let permit = self.permit().await;
+
+        if let Some((max_calls, window)) = self.max_calls {
+            loop {
+                let wait_until = {
+                    let now = Instant::now();
+                    let mut calls = self.calls.lock().unwrap();
+
+                    while calls.front().is_some_and(|call| *call + window <= now) {
+                        calls.pop_front();
+                    }
+
+                    if calls.len() < max_calls {
+                        calls.push_back(now);
+                        break;
+                    }
+
+                    calls[0] + window
+                };
+
+                sleep_until(wait_until).await;
+            }
+        }
+
+        permit
+    }
+
+    /// Waits until a call already let through by `max_calls` may run again.
+    pub(crate) async fn permit(&self) -> Option<OwnedSemaphorePermit> {
+        match &self.permits {
+            Some(permits) => Some(Arc::clone(permits).acquire_owned().await.unwrap()),
+            None => None,
+        }
+    }
+}
+
+impl Default for Governor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hash for Governor {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.max_concurrent.hash(state);
+        self.max_calls.hash(state);
+    }
+}
+
+/// Makes the source call once `governor` allows it, the returned stream holding its permit.
+pub(crate) async fn govern<St>(
+    governor: Option<&Governor>,
+    call: impl Future<Output = St>,
+) -> impl Stream<Item = St::Item>
+where
+    St: Stream,
+{
+    let permit = match governor {
+        Some(governor) => governor.acquire().await,
+        None => None,
+    };
+
+    call.await.map(move |item| {
+        let _permit = &permit;
+
+        item
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_max_calls() {
+        let governor = Governor::new().max_calls(2, Duration::from_millis(100));
+        let start = Instant::now();
+
+        for _ in 0..5 {
+            govern(Some(&governor), async { stream::iter([()]) })
+                .await
+                .collect::<Vec<_>>()
+                .await;
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent() {
+        let governor = Governor::new().max_concurrent(1);
+
+        let first = govern(Some(&governor), async { stream::iter([1]) }).await;
+
+        let second = govern(Some(&governor), async { stream::iter([2]) });
+        tokio::pin!(second);
+
+        assert!(futures::poll!(second.as_mut()).is_pending());
+
+        drop(first);
+
+        assert_eq!(second.await.collect::<Vec<_>>().await, [2]);
+    }
+}
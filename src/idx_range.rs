@@ -1,7 +1,7 @@
-use std::ops::{Range, RangeInclusive};
+use std::ops::{Range, RangeFrom, RangeFull, RangeInclusive};
 
 use crate::pages::{PageRange, PagesIter};
-use crate::Idx;
+use crate::{Idx, IndexDomain};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum IdxRange {
@@ -25,25 +25,77 @@ impl IdxRange {
         }
     }
 
-    pub fn pages<const PAGE_SIZE: Idx>(&self) -> impl Iterator<Item = PageRange<PAGE_SIZE>> {
+    pub fn start(&self) -> Idx {
+        match self {
+            IdxRange::One(idx) => *idx,
+            IdxRange::Many(start, _) => *start,
+        }
+    }
+
+    /// The index past the last one of the range.
+    pub fn end(&self) -> Idx {
+        match self {
+            IdxRange::One(idx) => idx + 1,
+            IdxRange::Many(_, end) => end + 1,
+        }
+    }
+
+    /// The indices in both ranges, `None` when they don't overlap.
+    pub fn intersect(&self, other: &IdxRange) -> Option<Self> {
+        let start = self.start().max(other.start());
+        let end = self.end().min(other.end());
+
+        IdxRange::new(start, end.saturating_sub(start) as usize)
+    }
+
+    /// Sorts `ranges` and joins the overlapping and adjacent ones.
+    pub fn merge(ranges: impl IntoIterator<Item = Self>) -> Vec<Self> {
+        let mut ranges = ranges.into_iter().collect::<Vec<_>>();
+        ranges.sort_by_key(IdxRange::start);
+
+        let mut merged: Vec<Self> = Vec::with_capacity(ranges.len());
+
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start() <= last.end() => {
+                    let end = last.end().max(range.end());
+
+                    *last = IdxRange::new(last.start(), (end - last.start()) as usize).unwrap();
+                }
+                _ => merged.push(range),
+            }
+        }
+
+        merged
+    }
+
+    /// Cuts the range at `end` (exclusive), `None` when nothing is left.
+    pub fn truncate(self, end: Idx) -> Option<Self> {
+        let start = self.start();
+
+        IdxRange::new(start, self.len().min(end.saturating_sub(start)) as usize)
+    }
+
+    pub fn pages<const PAGE_SIZE: Idx>(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = PageRange<PAGE_SIZE>> {
         PagesIter::from_idx_range(self)
     }
 }
 
 impl<I> TryFrom<Range<I>> for IdxRange
 where
-    I: Into<Idx> + Clone,
+    I: IndexDomain,
 {
     type Error = Range<I>;
 
     fn try_from(range: Range<I>) -> Result<Self, Range<I>> {
-        let start_index = range.start.clone().into();
-        let len = range
-            .end
-            .clone()
-            .into()
-            .checked_sub(start_index)
-            .unwrap_or(0) as usize;
+        let (Some(start_index), Some(end_index)) =
+            (range.start.clone().to_idx(), range.end.clone().to_idx())
+        else {
+            return Err(range);
+        };
+        let len = end_index.checked_sub(start_index).unwrap_or(0) as usize;
 
         IdxRange::new(start_index, len).ok_or(range)
     }
@@ -51,27 +103,29 @@ where
 
 impl<I> From<IdxRange> for Range<I>
 where
-    I: From<Idx> + Default,
+    I: IndexDomain,
 {
     fn from(range: IdxRange) -> Range<I> {
         match range {
-            IdxRange::One(idx) => idx.into()..(idx + 1).into(),
-            IdxRange::Many(from, to) => from.into()..(to + 1).into(),
+            IdxRange::One(idx) => I::from_idx(idx)..I::from_idx(idx + 1),
+            IdxRange::Many(from, to) => I::from_idx(from)..I::from_idx(to + 1),
         }
     }
 }
 
 impl<I> TryFrom<RangeInclusive<I>> for IdxRange
 where
-    I: Into<Idx> + Clone,
+    I: IndexDomain,
 {
     type Error = RangeInclusive<I>;
 
     fn try_from(range: RangeInclusive<I>) -> Result<Self, Self::Error> {
-        let start_index = range.start().clone().into();
-        let len = (range.end().clone().into() + 1)
-            .checked_sub(start_index)
-            .unwrap_or(0) as usize;
+        let (Some(start_index), Some(end_index)) =
+            (range.start().clone().to_idx(), range.end().clone().to_idx())
+        else {
+            return Err(range);
+        };
+        let len = (end_index + 1).checked_sub(start_index).unwrap_or(0) as usize;
 
         IdxRange::new(start_index, len).ok_or(range)
     }
@@ -79,16 +133,31 @@ where
 
 impl<I> From<IdxRange> for RangeInclusive<I>
 where
-    I: From<Idx>,
+    I: IndexDomain,
 {
     fn from(range: IdxRange) -> Self {
         match range {
-            IdxRange::One(idx) => idx.into()..=idx.into(),
-            IdxRange::Many(from, to) => from.into()..=to.into(),
+            IdxRange::One(idx) => I::from_idx(idx)..=I::from_idx(idx),
+            IdxRange::Many(from, to) => I::from_idx(from)..=I::from_idx(to),
         }
     }
 }
 
+impl<I> From<IdxRange> for RangeFrom<I>
+where
+    I: IndexDomain,
+{
+    fn from(range: IdxRange) -> Self {
+        I::from_idx(range.start())..
+    }
+}
+
+impl From<IdxRange> for RangeFull {
+    fn from(_range: IdxRange) -> Self {
+        ..
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,4 +197,57 @@ mod tests {
         let many: RangeInclusive<Idx> = many.into();
         assert_eq!(many, r);
     }
+
+    #[test]
+    fn test_signed_range() {
+        let r = -10..10i64;
+        let many = IdxRange::try_from(r.clone()).unwrap();
+        assert_eq!(many.len(), 20);
+        assert_eq!(Some(many.start()), (-10i64).to_idx());
+        let many: Range<i64> = many.into();
+        assert_eq!(many, r);
+
+        let r = -3..=-3i32;
+        let one = IdxRange::try_from(r.clone()).unwrap();
+        assert_eq!(Some(one.clone()), (-3i32).to_idx().map(IdxRange::One));
+        let one: RangeInclusive<i32> = one.into();
+        assert_eq!(one, r);
+
+        let (start, end) = (5, -5_i16);
+        assert!(IdxRange::try_from(start..end).is_err());
+    }
+
+    #[test]
+    fn test_truncate() {
+        let r = IdxRange::try_from(10..21 as Idx).unwrap();
+
+        assert_eq!(r.clone().truncate(100), Some(IdxRange::Many(10, 20)));
+        assert_eq!(r.clone().truncate(15), Some(IdxRange::Many(10, 14)));
+        assert_eq!(r.clone().truncate(11), Some(IdxRange::One(10)));
+        assert_eq!(r.truncate(10), None);
+    }
+
+    #[test]
+    fn test_merge() {
+        let ranges =
+            [20..30, 0..5, 25..40, 5..6, 50..51 as Idx].map(|r| IdxRange::try_from(r).unwrap());
+
+        assert_eq!(
+            IdxRange::merge(ranges),
+            [
+                IdxRange::Many(0, 5),
+                IdxRange::Many(20, 39),
+                IdxRange::One(50)
+            ]
+        );
+
+        let r = IdxRange::Many(10, 20);
+
+        assert_eq!(
+            r.intersect(&IdxRange::Many(15, 30)),
+            Some(IdxRange::Many(15, 20))
+        );
+        assert_eq!(r.intersect(&IdxRange::One(20)), Some(IdxRange::One(20)));
+        assert_eq!(r.intersect(&IdxRange::One(21)), None);
+    }
 }
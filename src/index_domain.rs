@@ -0,0 +1,81 @@
+use crate::Idx;
+
+/// Maps a source's index type onto the `Idx` page space, preserving the order.
+///
+/// Signed integers are biased by their sign bit, so `i64::MIN` lands on `0` and `0` on `2^63`:
+/// page boundaries line up with zero for power-of-two page sizes.
+/// The indexes past the range of a type saturate to its maximum.
+pub trait IndexDomain: Clone {
+    /// `None` for the values before the start of the page space, e.g. times before the origin of their scale.
+    fn to_idx(self) -> Option<Idx>;
+
+    fn from_idx(idx: Idx) -> Self;
+}
+
+macro_rules! unsigned_domain {
+    ($($u:ty),*) => {
+        $(
+            impl IndexDomain for $u {
+                fn to_idx(self) -> Option<Idx> {
+                    Some(self as Idx)
+                }
+
+                fn from_idx(idx: Idx) -> Self {
+                    <$u>::try_from(idx).unwrap_or(<$u>::MAX)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! signed_domain {
+    ($($i:ty => $u:ty),*) => {
+        $(
+            impl IndexDomain for $i {
+                fn to_idx(self) -> Option<Idx> {
+                    Some(((self as $u) ^ (1 << (<$u>::BITS - 1))) as Idx)
+                }
+
+                fn from_idx(idx: Idx) -> Self {
+                    (<$u>::try_from(idx).unwrap_or(<$u>::MAX) ^ (1 << (<$u>::BITS - 1))) as $i
+                }
+            }
+        )*
+    };
+}
+
+unsigned_domain!(u8, u16, u32, u64, u128, usize);
+
+signed_domain!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsigned() {
+        assert_eq!(42u32.to_idx(), Some(42));
+        assert_eq!(u64::from_idx(42), 42);
+        assert_eq!(u128::MAX.to_idx(), Some(Idx::MAX));
+        assert_eq!(u8::from_idx(256), u8::MAX);
+        assert_eq!(u32::from_idx(Idx::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn test_signed() {
+        assert_eq!(i64::MIN.to_idx(), Some(0));
+        assert_eq!(0i64.to_idx(), Some(1 << 63));
+        assert_eq!(i64::MAX.to_idx(), Some(u64::MAX as Idx));
+
+        let values = [i32::MIN, -1000, -1, 0, 1, 1000, i32::MAX];
+
+        for i in values {
+            assert_eq!(i.to_idx().map(i32::from_idx), Some(i));
+        }
+
+        assert!(values.windows(2).all(|w| w[0].to_idx() < w[1].to_idx()));
+
+        assert_eq!(i8::from_idx(256), i8::MAX);
+        assert_eq!(i64::from_idx(Idx::MAX), i64::MAX);
+    }
+}
@@ -3,13 +3,42 @@ pub use cachalot_proc_macro::cachalot;
 mod idx_range;
 pub use idx_range::IdxRange;
 
+mod index_domain;
+pub use index_domain::IndexDomain;
+
 pub mod pages;
 
+pub mod time;
+
 mod source;
 
+mod codec;
+pub use codec::PageCodec;
+
 mod config;
 pub use config::*;
 
+mod governor;
+pub use governor::Governor;
+
+mod retry;
+pub use retry::RetryPolicy;
+
+mod breaker;
+pub use breaker::{CircuitBreaker, NegativeCache};
+
+mod cancel;
+pub use cancel::CancelToken;
+
+mod write_behind;
+pub use write_behind::WriteBehind;
+
+mod summary;
+pub use summary::{PageSummary, Summary};
+
+mod pyramid;
+pub use pyramid::Pyramid;
+
 mod store;
 pub use store::*;
 
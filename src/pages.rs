@@ -34,13 +34,47 @@ pub struct PagesRange<const PAGE_SIZE: Idx> {
 }
 
 impl<const PAGE_SIZE: Idx> PagesRange<PAGE_SIZE> {
-    pub fn pages(&self) -> impl Iterator<Item = PageRange<PAGE_SIZE>> {
+    pub fn pages(&self) -> impl DoubleEndedIterator<Item = PageRange<PAGE_SIZE>> {
         PagesIter::from_pages_range(self)
     }
 
     pub fn len(&self) -> usize {
         (self.to + 1 - self.from) as usize
     }
+
+    /// The range without its first `pages` pages, `pages` being less than its length.
+    pub fn skip(&self, pages: usize) -> PagesRange<PAGE_SIZE> {
+        match pages {
+            0 => self.clone(),
+            pages => Self {
+                from: self.from + pages as Idx,
+                first: 0,
+                to: self.to,
+                last: self.last,
+            },
+        }
+    }
+
+    /// Splits the range into consecutive ranges of at most `pages` pages.
+    pub fn chunks(&self, pages: usize) -> impl Iterator<Item = PagesRange<PAGE_SIZE>> {
+        let range = self.clone();
+        let step = pages.max(1);
+
+        (range.from..=range.to).step_by(step).map(move |from| {
+            let to = (from + step as Idx - 1).min(range.to);
+
+            Self {
+                from,
+                first: if from == range.from { range.first } else { 0 },
+                to,
+                last: if to == range.to {
+                    range.last
+                } else {
+                    PAGE_SIZE - 1
+                },
+            }
+        })
+    }
 }
 
 impl<const PAGE_SIZE: Idx> Accumulable for PagesRange<PAGE_SIZE> {
@@ -97,6 +131,12 @@ impl<const PAGE_SIZE: Idx> From<&IdxRange> for PagesRange<PAGE_SIZE> {
     }
 }
 
+impl<const PAGE_SIZE: Idx> From<&PageRange<PAGE_SIZE>> for IdxRange {
+    fn from(page: &PageRange<PAGE_SIZE>) -> Self {
+        IdxRange::new(page.page * PAGE_SIZE + page.first, page.len()).unwrap()
+    }
+}
+
 impl<const PAGE_SIZE: Idx> Into<IdxRange> for PagesRange<PAGE_SIZE> {
     fn into(self) -> IdxRange {
         let start = self.from * PAGE_SIZE + self.first;
@@ -114,6 +154,27 @@ fn test_pages_range_into_idx_range() {
     assert_eq!(range, pages.into())
 }
 
+#[cfg(test)]
+#[test]
+fn test_pages_range_chunks() {
+    let range = IdxRange::new(7, 20).unwrap();
+    let pages = PagesRange::<3>::from(&range);
+
+    let chunks = pages
+        .chunks(3)
+        .map(|chunk| chunk.into())
+        .collect::<Vec<IdxRange>>();
+
+    assert_eq!(
+        chunks,
+        [
+            IdxRange::new(7, 8).unwrap(),
+            IdxRange::new(15, 9).unwrap(),
+            IdxRange::new(24, 3).unwrap(),
+        ]
+    );
+}
+
 #[derive(Debug)]
 pub struct PagesIter<const PAGE_SIZE: Idx> {
     first: Idx,
@@ -183,6 +244,27 @@ impl<const PAGE_SIZE: Idx> Iterator for PagesIter<PAGE_SIZE> {
     }
 }
 
+impl<const PAGE_SIZE: Idx> DoubleEndedIterator for PagesIter<PAGE_SIZE> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.left == 0 {
+            None
+        } else if self.left == 1 {
+            self.left = 0;
+
+            Some(PageRange::new(self.to, self.first, self.last))
+        } else {
+            self.left -= 1;
+
+            let page = PageRange::new(self.to, 0, self.last);
+
+            self.to -= 1;
+            self.last = PAGE_SIZE - 1;
+
+            Some(page)
+        }
+    }
+}
+
 #[cfg(test)]
 mod page_iter_tests {
     use super::*;
@@ -224,4 +306,20 @@ mod page_iter_tests {
         assert_eq!(pages.next(), Some(PageRange::new(4, 0, 2)));
         assert_eq!(pages.next(), None);
     }
+
+    #[test]
+    fn test_rev_pages() {
+        let r: IdxRange = (14..=22 as Idx).try_into().unwrap();
+        let mut pages = r.pages::<5>().rev();
+        assert_eq!(pages.next(), Some(PageRange::new(4, 0, 2)));
+        assert_eq!(pages.next(), Some(PageRange::new(3, 0, 4)));
+        assert_eq!(pages.next(), Some(PageRange::new(2, 4, 4)));
+        assert_eq!(pages.next(), None);
+
+        let mut pages = r.pages::<5>();
+        assert_eq!(pages.next_back(), Some(PageRange::new(4, 0, 2)));
+        assert_eq!(pages.next(), Some(PageRange::new(2, 4, 4)));
+        assert_eq!(pages.next_back(), Some(PageRange::new(3, 0, 4)));
+        assert_eq!(pages.next(), None);
+    }
 }
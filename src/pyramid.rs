@@ -0,0 +1,50 @@
+use std::any::Any;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+pub(crate) type Reduce<V> = Arc<dyn Fn(&[V]) -> V + Send + Sync>;
+
+/// Reduces the values of each level of the pyramid over the items into the values of the level above:
+/// every value of level `l + 1` is the reduction of `PAGE_SIZE` consecutive values of level `l`,
+/// level 0 holding the items themselves.
+///
+/// The reduction is erased, so it may be set in an untyped [`Config`](crate::Config);
+/// changing it for a cache already holding levels needs another root.
+#[derive(Clone)]
+pub struct Pyramid {
+    reduce: Arc<dyn Any + Send + Sync>,
+}
+
+impl Pyramid {
+    pub fn new<V: 'static>(reduce: impl Fn(&[V]) -> V + Send + Sync + 'static) -> Self {
+        let reduce: Reduce<V> = Arc::new(reduce);
+
+        Self {
+            reduce: Arc::new(reduce),
+        }
+    }
+
+    /// The reduction, `None` when it isn't over `V`.
+    pub(crate) fn reduce<V: 'static>(&self) -> Option<Reduce<V>> {
+        self.reduce.downcast_ref::<Reduce<V>>().cloned()
+    }
+}
+
+impl Hash for Pyramid {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.reduce).hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduce() {
+        let pyramid = Pyramid::new(|values: &[u32]| values.iter().sum());
+
+        assert_eq!(pyramid.reduce::<u32>().unwrap()(&[1, 2, 3]), 6);
+        assert!(pyramid.reduce::<u64>().is_none());
+    }
+}
@@ -0,0 +1,166 @@
+use std::any::{self, Any, TypeId};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::source::TrySource;
+
+type Retryable<E> = Arc<dyn Fn(&E) -> bool + Send + Sync>;
+
+/// How failed source calls are re-issued: up to `max_attempts` calls in total,
+/// waiting an exponential backoff with jitter between them.
+///
+/// Only the pages that were not cached yet are asked for again.
+pub struct RetryPolicy<E> {
+    pub max_attempts: usize,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    retryable: Option<Retryable<E>>,
+}
+
+impl<E> RetryPolicy<E> {
+    pub const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(100);
+    pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(10);
+
+    pub fn new(max_attempts: usize) -> Self {
+        Self {
+            max_attempts,
+            base_delay: Self::DEFAULT_BASE_DELAY,
+            max_delay: Self::DEFAULT_MAX_DELAY,
+            retryable: None,
+        }
+    }
+
+    /// A policy for the errors of `source`, without naming their type.
+    pub fn for_source<S, R, K, V>(_source: &S, max_attempts: usize) -> Self
+    where
+        S: TrySource<R, K, V, Error = E>,
+    {
+        Self::new(max_attempts)
+    }
+
+    pub fn backoff(mut self, base_delay: Duration, max_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Only retries the errors `retryable` accepts - all of them by default.
+    pub fn retry_if(mut self, retryable: impl Fn(&E) -> bool + Send + Sync + 'static) -> Self {
+        self.retryable = Some(Arc::new(retryable));
+        self
+    }
+
+    /// Whether `err`, returned by the call number `attempt` (from 0), is worth another call.
+    pub(crate) fn retries(&self, err: &E, attempt: usize) -> bool {
+        attempt + 1 < self.max_attempts
+            && self
+                .retryable
+                .as_ref()
+                .is_none_or(|retryable| retryable(err))
+    }
+
+    /// The delay before the call following the call number `attempt`, jittered within its upper half.
+    pub(crate) fn delay(&self, attempt: usize) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << attempt.min(31))
+            .min(self.max_delay);
+
+        let half = delay / 2;
+        let jitter = RandomState::new().hash_one(attempt) % (half.as_nanos() as u64 + 1);
+
+        half + Duration::from_nanos(jitter)
+    }
+}
+
+impl<E> Clone for RetryPolicy<E> {
+    fn clone(&self) -> Self {
+        Self {
+            max_attempts: self.max_attempts,
+            base_delay: self.base_delay,
+            max_delay: self.max_delay,
+            retryable: self.retryable.clone(),
+        }
+    }
+}
+
+impl<E> Hash for RetryPolicy<E> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.max_attempts.hash(state);
+        self.base_delay.hash(state);
+        self.max_delay.hash(state);
+    }
+}
+
+/// A [`RetryPolicy`] with its error type erased, so it may be set in an untyped [`Config`](crate::Config).
+#[derive(Clone)]
+pub(crate) struct AnyRetryPolicy {
+    error: TypeId,
+    policy: Arc<dyn Any + Send + Sync>,
+}
+
+impl AnyRetryPolicy {
+    pub(crate) fn new<E: 'static>(policy: RetryPolicy<E>) -> Self {
+        Self {
+            error: TypeId::of::<E>(),
+            policy: Arc::new(policy),
+        }
+    }
+
+    /// The policy for the errors `E`, panicking when it was set for another error type.
+    pub(crate) fn get<E: 'static>(&self) -> &RetryPolicy<E> {
+        self.policy
+            .downcast_ref::<RetryPolicy<E>>()
+            .unwrap_or_else(|| panic!("the retry policy isn't for {}", any::type_name::<E>()))
+    }
+}
+
+impl Hash for AnyRetryPolicy {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.error.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retries() {
+        let policy = RetryPolicy::new(3);
+
+        assert!(policy.retries(&"timeout", 0));
+        assert!(policy.retries(&"timeout", 1));
+        assert!(!policy.retries(&"timeout", 2));
+
+        let policy = policy.retry_if(|err: &&str| *err == "timeout");
+
+        assert!(policy.retries(&"timeout", 0));
+        assert!(!policy.retries(&"not found", 0));
+
+        let policy = AnyRetryPolicy::new(policy);
+
+        assert!(policy.get::<&str>().retries(&"timeout", 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_retries_other_error() {
+        AnyRetryPolicy::new(RetryPolicy::<&str>::new(3)).get::<u32>();
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy =
+            RetryPolicy::<()>::new(10).backoff(Duration::from_millis(100), Duration::from_secs(1));
+
+        for (attempt, max) in [(0, 100), (1, 200), (2, 400), (3, 800), (4, 1000), (9, 1000)] {
+            let delay = policy.delay(attempt);
+
+            assert!(delay >= Duration::from_millis(max / 2));
+            assert!(delay <= Duration::from_millis(max));
+        }
+    }
+}
@@ -1,25 +1,69 @@
+use std::ops::{RangeFrom, RangeFull};
+
 use futures::{Future, Stream};
 
-use bytemuck::{AnyBitPattern, NoUninit};
+use crate::{Idx, IdxRange, IndexDomain};
 
-use crate::IdxRange;
+pub trait SourceRange: From<IdxRange> + Sized {
+    /// Open ranges leave it to the source where to stop, so the items past the requested pages are dropped.
+    const OPEN: bool = false;
+    /// Ranges without a start have the source yield from the first item, so the items before the requested pages are dropped.
+    const FROM_FIRST: bool = false;
 
-pub trait SourceRange: From<IdxRange> + TryInto<IdxRange, Error = Self> {}
+    fn resolve(self, end: Option<Idx>) -> Result<IdxRange, Self>;
+}
 
-impl<Range> SourceRange for Range where Range: From<IdxRange> + TryInto<IdxRange, Error = Range> {}
+impl<Range> SourceRange for Range
+where
+    Range: From<IdxRange> + TryInto<IdxRange, Error = Range>,
+{
+    fn resolve(self, _end: Option<Idx>) -> Result<IdxRange, Self> {
+        self.try_into()
+    }
+}
 
-pub trait Source<R, K, V>: Fn(K, R) -> Self::Fut + Sync
+impl<I> SourceRange for RangeFrom<I>
 where
-    V: NoUninit + AnyBitPattern,
+    I: IndexDomain,
 {
+    const OPEN: bool = true;
+
+    fn resolve(self, end: Option<Idx>) -> Result<IdxRange, Self> {
+        match end {
+            Some(end) => match self.start.clone().to_idx() {
+                Some(start) => IdxRange::try_from(start..end).map_err(|_| self),
+                None => Err(self),
+            },
+            None => Err(self),
+        }
+    }
+}
+
+impl SourceRange for RangeFull {
+    const OPEN: bool = true;
+    const FROM_FIRST: bool = true;
+
+    fn resolve(self, end: Option<Idx>) -> Result<IdxRange, Self> {
+        match end {
+            Some(end) => IdxRange::try_from(0..end).map_err(|_| self),
+            None => Err(self),
+        }
+    }
+}
+
+pub trait Source<R, K, V>: Fn(K, R) -> Self::Fut + Sync {
     type Stream: Stream<Item = V> + Send;
     type Fut: Future<Output = Self::Stream> + Send;
+
+    /// The end of the data for `k`, which the loads use over `config.end`; unknown by default.
+    fn end(&self, _k: &K) -> Option<Idx> {
+        None
+    }
 }
 
 impl<F, R, K, V, St, Fut> Source<R, K, V> for F
 where
     F: Fn(K, R) -> Fut + Sync,
-    V: NoUninit + AnyBitPattern,
     St: Stream<Item = V> + Send,
     Fut: Future<Output = St> + Send,
 {
@@ -27,19 +71,64 @@ where
     type Fut = Fut;
 }
 
-pub trait TrySource<R, K, V>: Fn(K, R) -> Self::Fut + Sync
-where
-    V: NoUninit + AnyBitPattern,
-{
+pub trait TrySource<R, K, V>: Fn(K, R) -> Self::Fut + Sync {
     type Error: Send + Sync + 'static;
     type Stream: Stream<Item = Result<V, Self::Error>> + Send;
     type Fut: Future<Output = Self::Stream> + Send;
+
+    /// The end of the data for `k`, which the loads use over `config.end`; unknown by default.
+    fn end(&self, _k: &K) -> Option<Idx> {
+        None
+    }
 }
 
 impl<F, R, K, V, E, St, Fut> TrySource<R, K, V> for F
 where
     F: Fn(K, R) -> Fut + Sync,
-    V: NoUninit + AnyBitPattern,
+    E: Send + Sync + 'static,
+    St: Stream<Item = Result<V, E>> + Send,
+    Fut: Future<Output = St> + Send,
+{
+    type Error = E;
+    type Stream = St;
+    type Fut = Fut;
+}
+
+/// A source fetching whole pages by their number, so that missing pages can be filled independently.
+pub trait PageSource<K, V>: Fn(K, Idx) -> Self::Fut + Sync {
+    type Stream: Stream<Item = V> + Send;
+    type Fut: Future<Output = Self::Stream> + Send;
+
+    /// The end of the data for `k`, which the loads use over `config.end`; unknown by default.
+    fn end(&self, _k: &K) -> Option<Idx> {
+        None
+    }
+}
+
+impl<F, K, V, St, Fut> PageSource<K, V> for F
+where
+    F: Fn(K, Idx) -> Fut + Sync,
+    St: Stream<Item = V> + Send,
+    Fut: Future<Output = St> + Send,
+{
+    type Stream = St;
+    type Fut = Fut;
+}
+
+pub trait TryPageSource<K, V>: Fn(K, Idx) -> Self::Fut + Sync {
+    type Error: Send + Sync + 'static;
+    type Stream: Stream<Item = Result<V, Self::Error>> + Send;
+    type Fut: Future<Output = Self::Stream> + Send;
+
+    /// The end of the data for `k`, which the loads use over `config.end`; unknown by default.
+    fn end(&self, _k: &K) -> Option<Idx> {
+        None
+    }
+}
+
+impl<F, K, V, E, St, Fut> TryPageSource<K, V> for F
+where
+    F: Fn(K, Idx) -> Fut + Sync,
     E: Send + Sync + 'static,
     St: Stream<Item = Result<V, E>> + Send,
     Fut: Future<Output = St> + Send,
@@ -0,0 +1,36 @@
+use thiserror::Error;
+
+use crate::IdxRange;
+
+use super::StoreError;
+
+/// Consecutive values of a page, with the indices they were loaded for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chunk<V> {
+    pub range: IdxRange,
+    pub values: Vec<V>,
+}
+
+/// A chunk along with the loaded range it belongs to.
+pub type TaggedChunk<V> = (IdxRange, Chunk<V>);
+
+/// A part of the loaded range that was neither cached nor filled from the source.
+#[derive(Error, Debug)]
+#[error("{error} - gap: {range:?}")]
+pub struct GapError<E> {
+    pub range: IdxRange,
+    #[source]
+    pub error: StoreError<E>,
+}
+
+impl<V> Chunk<V> {
+    pub(super) fn new(range: IdxRange, values: Vec<V>) -> Self {
+        Self { range, values }
+    }
+}
+
+impl<E> GapError<E> {
+    pub(super) fn new(range: IdxRange, error: StoreError<E>) -> Self {
+        Self { range, error }
+    }
+}
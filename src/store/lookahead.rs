@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::future::BoxFuture;
+use futures::stream::{BoxStream, Fuse, FusedStream, FuturesOrdered, Stream, StreamExt};
+
+use super::{Chunk, GapError, PagesStream};
+
+type Run<'a, V, E> = Result<PagesStream<'a, V, E>, GapError<E>>;
+
+/// A run to load, and whether loading it calls the source.
+pub(super) type PlannedRun<'a, V, E> = (bool, BoxFuture<'a, Run<'a, V, E>>);
+
+/// Flattens `runs` in order, while starting the runs ahead of the current one,
+/// so the upcoming ones make progress as the current one is consumed.
+///
+/// At most `prefetch` of the runs ahead call the source, and at most `2 * prefetch` are ahead at all,
+/// which covers `prefetch` uncached runs as the cached and uncached ones alternate.
+pub(super) struct Lookahead<'a, V, E> {
+    runs: Fuse<BoxStream<'a, PlannedRun<'a, V, E>>>,
+    next: Option<PlannedRun<'a, V, E>>,
+    pending: FuturesOrdered<BoxFuture<'a, Run<'a, V, E>>>,
+    ready: VecDeque<Run<'a, V, E>>,
+    current: Option<PagesStream<'a, V, E>>,
+    // whether each run started, from the current one on, calls the source
+    calls: VecDeque<bool>,
+    prefetch: usize,
+}
+
+impl<'a, V, E> Lookahead<'a, V, E> {
+    pub(super) fn new(runs: BoxStream<'a, PlannedRun<'a, V, E>>, prefetch: usize) -> Self {
+        Self {
+            runs: runs.fuse(),
+            next: None,
+            pending: FuturesOrdered::new(),
+            ready: VecDeque::new(),
+            current: None,
+            calls: VecDeque::new(),
+            prefetch: prefetch.max(1),
+        }
+    }
+
+    fn calls_ahead(&self) -> usize {
+        self.calls.iter().skip(1).filter(|calls| **calls).count()
+    }
+
+    /// Starts the next runs up to the limits, keeping aside the first one past them.
+    fn start(&mut self, cx: &mut Context<'_>) {
+        while self.calls.len() < 2 * self.prefetch + 1 {
+            let (calls, run) = match self.next.take() {
+                Some(run) => run,
+                None => match self.runs.poll_next_unpin(cx) {
+                    Poll::Ready(Some(run)) => run,
+                    _ => break,
+                },
+            };
+
+            if calls && !self.calls.is_empty() && self.calls_ahead() == self.prefetch {
+                self.next = Some((calls, run));
+                break;
+            }
+
+            self.calls.push_back(calls);
+            self.pending.push_back(run);
+        }
+    }
+}
+
+// the runs are boxed, and the buffered ones are never pinned
+impl<V, E> Unpin for Lookahead<'_, V, E> {}
+
+impl<'a, V, E> Stream for Lookahead<'a, V, E> {
+    type Item = Result<Chunk<V>, GapError<E>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            this.start(cx);
+
+            while let Poll::Ready(Some(run)) = this.pending.poll_next_unpin(cx) {
+                this.ready.push_back(run);
+            }
+
+            let current = match &mut this.current {
+                Some(current) => current,
+                None => match this.ready.pop_front() {
+                    Some(Ok(pages)) => {
+                        this.current = Some(pages);
+                        continue;
+                    }
+                    Some(Err(err)) => {
+                        this.calls.pop_front();
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    None if this.runs.is_terminated()
+                        && this.next.is_none()
+                        && this.pending.is_empty() =>
+                    {
+                        return Poll::Ready(None)
+                    }
+                    None => return Poll::Pending,
+                },
+            };
+
+            match current.poll_next_unpin(cx) {
+                Poll::Ready(None) => {
+                    this.calls.pop_front();
+                    this.current = None;
+                }
+                poll => return poll,
+            }
+        }
+    }
+}
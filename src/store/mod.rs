@@ -1,20 +1,37 @@
 use std::borrow::Cow;
-use std::fs::File;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
 use std::hash::{BuildHasher, Hash, Hasher};
-use std::path::Path;
+use std::io;
+use std::iter;
+use std::mem;
+use std::num::NonZero;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use thiserror::Error;
 
-use futures::pin_mut;
-use futures::stream::{Stream, StreamExt, TryStreamExt};
+use bytemuck::{AnyBitPattern, NoUninit};
 
-use tokio::task;
+use futures::future::{self, BoxFuture, Either};
+use futures::stream::{self, BoxStream, Stream, StreamExt, TryStreamExt};
+use futures::{pin_mut, Future, FutureExt, TryFutureExt};
 
-use bytemuck::{AnyBitPattern, NoUninit};
+use tokio::fs::{create_dir_all, read, remove_file, try_exists};
+use tokio::sync::mpsc;
+use tokio::task;
+use tokio::time::{sleep, timeout};
 
 use crate::pages::{PageRange, PagesRange};
-use crate::{Idx, TypedConfig};
+use crate::pyramid::Reduce;
+use crate::retry::AnyRetryPolicy;
+use crate::source::SourceRange;
+use crate::summary::{summarize, Summary};
+use crate::{
+    CancelToken, CircuitBreaker, Config, Governor, Idx, IdxRange, NegativeCache, PageSummary,
+    TypedConfig, WriteBehind,
+};
 
 mod store;
 pub use store::*;
@@ -22,11 +39,217 @@ pub use store::*;
 mod try_store;
 pub use try_store::*;
 
+mod page_store;
+pub use page_store::*;
+
+mod try_page_store;
+pub use try_page_store::*;
+
+mod chunk;
+pub use chunk::*;
+
+mod page_format;
+pub use page_format::*;
+
+mod lookahead;
+use lookahead::Lookahead;
+
 mod store_pages;
 pub(super) use store_pages::*;
 
 type PagesDir = Arc<Cow<'static, Path>>;
 type PagePath = Cow<'static, Path>;
+type PagesStream<'a, V, E = ()> = BoxStream<'a, Result<Chunk<V>, GapError<E>>>;
+
+#[derive(Clone, Copy)]
+pub(super) struct SourceBounds {
+    end_of_data: bool,
+    open: bool,
+    from_first: bool,
+}
+
+impl SourceBounds {
+    pub(super) fn new<R: SourceRange, const PAGE_SIZE: Idx>(config: &Config<PAGE_SIZE>) -> Self {
+        Self {
+            end_of_data: config.end.is_some(),
+            open: R::OPEN,
+            from_first: R::FROM_FIRST,
+        }
+    }
+}
+
+/// How source calls are made: whether they are made at all, when the governor allows them,
+/// and how failed ones are retried.
+#[derive(Clone)]
+pub(super) struct SourcePolicy {
+    governor: Option<Arc<Governor>>,
+    retry: Option<AnyRetryPolicy>,
+    breaker: Option<Arc<CircuitBreaker>>,
+    negative_cache: Option<Arc<NegativeCache>>,
+    call_timeout: Option<Duration>,
+    item_timeout: Option<Duration>,
+}
+
+impl SourcePolicy {
+    pub(super) fn new<const PAGE_SIZE: Idx>(config: &Config<PAGE_SIZE>) -> Self {
+        Self {
+            governor: config.governor.clone(),
+            retry: config.retry.clone(),
+            breaker: config.breaker.clone(),
+            negative_cache: config.negative_cache.clone(),
+            call_timeout: config.call_timeout,
+            item_timeout: config.item_timeout,
+        }
+    }
+
+    /// Fails right away when the breaker is open, or when the pages failed recently,
+    /// with their error again when the negative cache replays it.
+    fn admit<E: 'static, const PAGE_SIZE: Idx>(
+        &self,
+        dir: &Path,
+        pages: &PagesRange<PAGE_SIZE>,
+    ) -> Result<(), StoreError<E>> {
+        if let Some(failures) = self.breaker.as_ref().and_then(|breaker| breaker.open(dir)) {
+            return Err(StoreError::CircuitOpen(failures));
+        }
+
+        let (start, end) = idx_bounds(pages);
+
+        match self
+            .negative_cache
+            .as_ref()
+            .and_then(|cache| cache.get(dir, start, end))
+        {
+            Some((_, _, Some(cause))) => Err(cause),
+            Some((first, last, None)) => Err(StoreError::RecentFailure(first, last)),
+            None => Ok(()),
+        }
+    }
+
+    /// Records the outcome of a call, once its retries are over.
+    fn record(&self, dir: &Path, success: bool) {
+        if let Some(breaker) = &self.breaker {
+            breaker.record(dir, success);
+        }
+    }
+
+    /// Remembers the pages whose call failed for good, and with which error.
+    fn remember<E: 'static, const PAGE_SIZE: Idx>(
+        &self,
+        dir: &Path,
+        pages: &PagesRange<PAGE_SIZE>,
+        err: &StoreError<E>,
+    ) {
+        if let Some(cache) = &self.negative_cache {
+            let (start, end) = idx_bounds(pages);
+
+            cache.insert(dir, start, end, err);
+        }
+    }
+
+    /// Makes the call once the governor allows it, failing it past the timeouts.
+    /// The returned stream holds the permit of the call, and borrows nothing from `self`.
+    async fn call<'s, V, E, St>(
+        &self,
+        call: impl Future<Output = St>,
+    ) -> BoxStream<'s, Result<V, StoreError<E>>>
+    where
+        V: Send + 's,
+        E: Send + 's,
+        St: Stream<Item = Result<V, E>> + Send + 's,
+    {
+        self.governed_call(call, false).await
+    }
+
+    /// Makes the call like `call`, for a run that may be read only later:
+    /// its permit is given back once the source has answered, and taken again when the stream is first read,
+    /// so that a parked stream does not keep the calls of the runs before it waiting.
+    async fn call_ahead<'s, V, E, St>(
+        &self,
+        call: impl Future<Output = St>,
+    ) -> BoxStream<'s, Result<V, StoreError<E>>>
+    where
+        V: Send + 's,
+        E: Send + 's,
+        St: Stream<Item = Result<V, E>> + Send + 's,
+    {
+        self.governed_call(call, true).await
+    }
+
+    async fn governed_call<'s, V, E, St>(
+        &self,
+        call: impl Future<Output = St>,
+        ahead: bool,
+    ) -> BoxStream<'s, Result<V, StoreError<E>>>
+    where
+        V: Send + 's,
+        E: Send + 's,
+        St: Stream<Item = Result<V, E>> + Send + 's,
+    {
+        let (call_timeout, item_timeout) = (self.call_timeout, self.item_timeout);
+
+        let mut permit = match &self.governor {
+            Some(governor) => governor.acquire().await,
+            None => None,
+        };
+
+        let source = match call_timeout {
+            Some(limit) => timeout(limit, call).await.ok(),
+            None => Some(call.await),
+        };
+
+        let source = match source {
+            Some(source) => Either::Left(source.map_err(StoreError::External)),
+            None => Either::Right(stream::iter([Err(StoreError::CallTimeout(
+                call_timeout.unwrap(),
+            ))])),
+        };
+
+        let governor = match ahead {
+            true => permit.take().and(self.governor.clone()),
+            false => None,
+        };
+
+        async_stream::stream! {
+            let _permit = match governor {
+                Some(governor) => governor.permit().await,
+                None => permit,
+            };
+            pin_mut!(source);
+
+            loop {
+                let item = match item_timeout {
+                    Some(limit) => timeout(limit, source.next())
+                        .await
+                        .unwrap_or(Some(Err(StoreError::ItemTimeout(limit)))),
+                    None => source.next().await,
+                };
+
+                match item {
+                    Some(Err(err)) => {
+                        yield Err(err);
+                        break;
+                    }
+                    Some(item) => yield item,
+                    None => break,
+                }
+            }
+        }
+        .boxed()
+    }
+
+    fn retries<E: 'static>(&self, err: &E, attempt: usize) -> bool {
+        self.retry
+            .as_ref()
+            .is_some_and(|retry| retry.get::<E>().retries(err, attempt))
+    }
+
+    async fn backoff<E: 'static>(&self, attempt: usize) {
+        if let Some(retry) = &self.retry {
+            sleep(retry.get::<E>().delay(attempt)).await;
+        }
+    }
+}
 
 #[derive(Debug, Error)]
 pub enum StoreError<E = ()> {
@@ -38,54 +261,127 @@ pub enum StoreError<E = ()> {
     PageWrite(Cow<'static, Path>),
     #[error("Page read error - path: {0}")]
     PageRead(Cow<'static, Path>),
+    #[error("Source length error - page: {0}; expected: {1}; actual: {2}")]
+    SourceLength(Idx, usize, usize),
+    #[error("Source index error - page: {0}; index out of order or out of the range: {1}")]
+    SourceIndex(Idx, Idx),
     #[error("Path access error - path: {1}; io-error: {0}")]
     PathAccess(std::io::Error, Cow<'static, Path>),
+    #[error("Circuit open error - the source failed {0} times in a row")]
+    CircuitOpen(usize),
+    #[error("Recent failure error - range: {0}..={1}")]
+    RecentFailure(Idx, Idx),
+    #[error("Call timeout error - the source call took over {0:?}")]
+    CallTimeout(Duration),
+    #[error("Item timeout error - the source took over {0:?} for an item")]
+    ItemTimeout(Duration),
+    #[error("Cancelled error - the load was cancelled")]
+    Cancelled,
+    #[error("Unbounded range error - open ranges need the end of the data here")]
+    Unbounded,
     #[error("external error")]
     External(E),
 }
 
-fn load_pages<'a, V, E, const PAGE_SIZE: Idx>(
+impl<E> StoreError<E> {
+    /// The source error, or the store failure when there is none, e.g. an open circuit breaker.
+    pub fn external(self) -> Result<E, StoreError> {
+        Err(match self {
+            StoreError::External(err) => return Ok(err),
+            StoreError::FileCreation(err, path) => StoreError::FileCreation(err, path),
+            StoreError::FileOpen(err, path) => StoreError::FileOpen(err, path),
+            StoreError::PageWrite(path) => StoreError::PageWrite(path),
+            StoreError::PageRead(path) => StoreError::PageRead(path),
+            StoreError::SourceLength(page, expected, actual) => {
+                StoreError::SourceLength(page, expected, actual)
+            }
+            StoreError::SourceIndex(page, idx) => StoreError::SourceIndex(page, idx),
+            StoreError::PathAccess(err, path) => StoreError::PathAccess(err, path),
+            StoreError::CircuitOpen(failures) => StoreError::CircuitOpen(failures),
+            StoreError::RecentFailure(first, last) => StoreError::RecentFailure(first, last),
+            StoreError::CallTimeout(timeout) => StoreError::CallTimeout(timeout),
+            StoreError::ItemTimeout(timeout) => StoreError::ItemTimeout(timeout),
+            StoreError::Cancelled => StoreError::Cancelled,
+            StoreError::Unbounded => StoreError::Unbounded,
+        })
+    }
+
+    /// The source error, or the store failure converted into the source error type.
+    fn into_external(self) -> E
+    where
+        E: From<StoreError>,
+    {
+        self.external().unwrap_or_else(E::from)
+    }
+
+    /// Whether the load was stopped on purpose, by its token or a timeout, rather than failed.
+    fn interrupts(&self) -> bool {
+        matches!(
+            self,
+            StoreError::Cancelled | StoreError::CallTimeout(_) | StoreError::ItemTimeout(_)
+        )
+    }
+
+    /// Whether the source call was not made, the source being deemed down.
+    fn refuses(&self) -> bool {
+        matches!(
+            self,
+            StoreError::CircuitOpen(_) | StoreError::RecentFailure(..)
+        )
+    }
+
+    /// Whether the error comes from the source call, rather than from the cache.
+    fn is_source_failure(&self) -> bool {
+        matches!(
+            self,
+            StoreError::External(_) | StoreError::CallTimeout(_) | StoreError::ItemTimeout(_)
+        )
+    }
+}
+
+fn load_pages<'a, F, V, E, const PAGE_SIZE: Idx>(
     dir: PagesDir,
     pages: PagesRange<PAGE_SIZE>,
-) -> impl Stream<Item = Result<Vec<V>, StoreError<E>>> + 'a
+) -> impl Stream<Item = Result<Chunk<V>, GapError<E>>> + 'a
 where
-    V: NoUninit + AnyBitPattern + Send,
+    F: PageFormat<V>,
+    V: Send + 'static,
     E: Send + 'static,
 {
     async_stream::stream! {
         for page in pages.pages() {
+            let range = IdxRange::from(&page);
             let page_path = page_path(dir.as_ref(), &page.page);
 
-            yield load_page(page_path, page).await;
+            yield chunk_or_gap(range, load_page::<F, _, _, PAGE_SIZE>(page_path, page).await);
         }
     }
 }
 
-async fn load_page<'a, V, E, const PAGE_SIZE: Idx>(
+fn chunk_or_gap<V, E>(
+    range: IdxRange,
+    result: Result<Vec<V>, StoreError<E>>,
+) -> Result<Chunk<V>, GapError<E>> {
+    match result {
+        Ok(values) => Ok(Chunk::new(range, values)),
+        Err(err) => Err(GapError::new(range, err)),
+    }
+}
+
+async fn load_page<'a, F, V, E, const PAGE_SIZE: Idx>(
     page_path: PagePath,
     page: PageRange<PAGE_SIZE>,
 ) -> Result<Vec<V>, StoreError<E>>
 where
-    V: NoUninit + AnyBitPattern + Send,
+    F: PageFormat<V>,
+    V: Send + 'static,
     E: Send + 'static,
 {
-    use std::io::{Read, Seek, SeekFrom};
-    use std::mem::size_of;
-
-    use bytemuck::cast_slice_mut;
-
     let join = task::spawn_blocking(move || {
         let mut file = File::open(page_path.as_ref())
             .map_err(|err| StoreError::FileOpen(err, page_path.clone()))?;
-        file.seek(SeekFrom::Start(page.first as u64 * size_of::<V>() as u64))
-            .map_err(|_| StoreError::PageRead(page_path.clone()))?;
-
-        let mut buf = vec![V::zeroed(); page.len()];
 
-        file.read(cast_slice_mut(&mut buf))
-            .map_err(|_| StoreError::PageRead(page_path.clone()))?;
-
-        Ok(buf)
+        F::read(&mut file, &page).map_err(|_| StoreError::PageRead(page_path.clone()))
     });
 
     match join.await {
@@ -96,94 +392,1548 @@ where
     }
 }
 
-pub(super) async fn cache_pages<'a, V, const PAGE_SIZE: Idx>(
+pub(super) async fn cache_pages<'a, F, V, E, const PAGE_SIZE: Idx>(
     dir: PagesDir,
     pages: PagesRange<PAGE_SIZE>,
-    source: impl Stream<Item = V> + 'a,
-) -> impl Stream<Item = Result<Vec<V>, StoreError>> + 'a
+    source: impl Stream<Item = Result<V, StoreError<E>>> + 'a,
+    bounds: SourceBounds,
+    writer: Option<Arc<WriteBehind>>,
+    summary: Option<PageSummary>,
+) -> impl Stream<Item = Result<Chunk<V>, StoreError<E>>> + 'a
 where
-    V: NoUninit + Send,
+    F: PageFormat<V>,
+    V: Send + 'static,
+    E: Send + 'static,
 {
-    async_stream::stream! {
+    let call: IdxRange = pages.clone().into();
+    let source = from_call_start::<F, _, _>(source, call.start(), bounds);
+
+    async_stream::try_stream! {
+        let source = source.peekable();
         pin_mut!(source);
 
-        for page in pages.pages() {
-            let page_data = source.by_ref().take(page.len()).collect::<Vec<_>>().await;
+        let mut last = None;
+        let mut pages = pages.pages().peekable();
+
+        while let Some(page) = pages.next() {
+            let range = IdxRange::from(&page);
+            let mut page_data = Vec::new();
+
+            loop {
+                // a misplaced item would leave the pages before it empty, so it fails the page
+                if let Some(Ok(item)) = source.as_mut().peek().await {
+                    if let Some(idx) = F::index(item) {
+                        check_index::<_, PAGE_SIZE>(&page, &call, bounds, last, idx)?;
+                    }
+                }
+
+                let Some(item) = source
+                    .as_mut()
+                    .next_if(|item| match item {
+                        Ok(item) => F::fits(&page, item, page_data.len()),
+                        Err(_) => true,
+                    })
+                    .await
+                else {
+                    break;
+                };
+
+                let item = item?;
+                last = F::index(&item).or(last);
+                page_data.push(item);
+            }
+
+            if bounds.end_of_data
+                && page_data.len() < F::expected(&page, page_data.len())
+                && source.as_mut().peek().await.is_none()
+            {
+                let start = range.start();
 
-            yield cache_page(dir.as_ref(), page, page_data).await;
+                if let Some(range) = range.truncate(start + page_data.len() as Idx) {
+                    yield Chunk::new(range, page_data);
+                }
+
+                break;
+            }
+
+            let extra = match pages.peek() {
+                Some(_) => 0,
+                None if bounds.open => 0,
+                // one extra item shows the source is too long, and the source may never end
+                None => source.as_mut().take(1).count().await,
+            };
+
+            check_page_len::<F, _, _, PAGE_SIZE>(&page, page_data.len(), extra)?;
+
+            yield Chunk::new(range, cache_page::<F, _, _, PAGE_SIZE>(dir.as_ref(), page, page_data, writer.as_deref(), summary.as_ref()).await?);
         }
     }
 }
 
-pub(super) async fn try_cache_pages<'a, V, E, const PAGE_SIZE: Idx>(
+/// How the pages of a source call are cached: only while the load stream is consumed,
+/// or to the end of the call, even once the load stream is dropped.
+pub(super) trait Detach<'s>: Copy + Send + 's {
+    fn detach<T: Send + 'static>(self, pages: BoxStream<'s, T>) -> BoxStream<'s, T>;
+}
+
+/// Caches the pages of a call as the load stream is consumed, and drops the call with it.
+#[derive(Clone, Copy)]
+pub(super) struct Attached;
+
+impl<'s> Detach<'s> for Attached {
+    fn detach<T: Send + 'static>(self, pages: BoxStream<'s, T>) -> BoxStream<'s, T> {
+        pages
+    }
+}
+
+/// Caches the pages of a call in a background task,
+/// which finishes the call once the load stream is dropped.
+#[derive(Clone, Copy)]
+pub(super) struct Detached;
+
+impl Detach<'static> for Detached {
+    fn detach<T: Send + 'static>(self, mut pages: BoxStream<'static, T>) -> BoxStream<'static, T> {
+        let (sender, receiver) = mpsc::channel(1);
+
+        tokio::spawn(async move {
+            while let Some(page) = pages.next().await {
+                // the load stream is gone, the rest of the call is only cached
+                let _ = sender.send(page).await;
+            }
+        });
+
+        stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|page| (page, receiver))
+        })
+        .boxed()
+    }
+}
+
+/// Fills `pages` from `source` until `config.cancel` is cancelled.
+pub(super) async fn fill_pages<'a, 's, F, V, E, St, Fut, const PAGE_SIZE: Idx>(
     dir: PagesDir,
     pages: PagesRange<PAGE_SIZE>,
-    source: impl Stream<Item = Result<V, E>> + 'a,
-) -> impl Stream<Item = Result<Vec<V>, StoreError<E>>> + 'a
+    source: impl Fn(PagesRange<PAGE_SIZE>) -> Fut + Send + 'a,
+    bounds: SourceBounds,
+    config: &Config<PAGE_SIZE>,
+    detach: impl Detach<'s>,
+) -> PagesStream<'a, V, E>
 where
-    V: NoUninit + Send,
+    's: 'a,
+    F: PageFormat<V>,
+    V: Send + Sync + 'static,
     E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send + 's,
+    Fut: Future<Output = St> + Send + 'a,
 {
-    async_stream::try_stream! {
-        pin_mut!(source);
+    let range = pages.clone().into();
+    let pages =
+        fill_chunks::<F, V, E, _, _, PAGE_SIZE>(dir, pages, source, bounds, config, detach).await;
 
-        for page in pages.pages() {
-            let page_data = source
-                .by_ref()
-                .take(page.len())
-                .try_collect::<Vec<_>>()
+    cancellable(pages, range, config.cancel.clone()).boxed()
+}
+
+/// Fills `pages` from `source`, asking it for at most `config.max_source_range` items per call.
+/// The first call is started right away, the next ones once the previous pages are consumed,
+/// each call waiting for `config.governor` to allow it.
+/// The first call holds no permit of the governor between its answer and the first read of its pages.
+/// A call failing with a retryable error is made again for the failed page alone, as `config.retry` says,
+/// and the pages past it are asked for by the next call.
+/// The pages of each call are cached as `detach` says.
+async fn fill_chunks<'a, 's, F, V, E, St, Fut, const PAGE_SIZE: Idx>(
+    dir: PagesDir,
+    pages: PagesRange<PAGE_SIZE>,
+    source: impl Fn(PagesRange<PAGE_SIZE>) -> Fut + Send + 'a,
+    bounds: SourceBounds,
+    config: &Config<PAGE_SIZE>,
+    detach: impl Detach<'s>,
+) -> PagesStream<'a, V, E>
+where
+    's: 'a,
+    F: PageFormat<V>,
+    V: Send + Sync + 'static,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send + 's,
+    Fut: Future<Output = St> + Send + 'a,
+{
+    let policy = SourcePolicy::new(config);
+    let writer = config.write_behind.clone();
+    let summary = config.summary.clone();
+
+    let chunk = match config.max_source_range {
+        Some(max) => (max / PAGE_SIZE).clamp(1, usize::MAX as Idx) as usize,
+        None => pages.len(),
+    };
+
+    let mut chunks = pages.chunks(chunk);
+
+    let first = chunks.next().unwrap();
+    let first_call = match policy.admit::<E, PAGE_SIZE>(dir.as_ref(), &first) {
+        Ok(()) => Some(policy.call_ahead(source(first.clone())).await),
+        Err(_) => None,
+    };
+
+    let chunks = iter::once((first, first_call)).chain(chunks.map(|pages| (pages, None)));
+
+    async_stream::stream! {
+        for (chunk, mut call) in chunks {
+            // the pages of the chunk left to fill
+            let mut left = Some(chunk);
+            let mut attempt = 0;
+
+            while let Some(pages) = left.take() {
+                // a retry asks for the failed page alone
+                let asked = match attempt {
+                    0 => pages.clone(),
+                    _ => pages.chunks(1).next().unwrap(),
+                };
+
+                let source = match call.take() {
+                    Some(call) => call,
+                    None => match policy.admit(dir.as_ref(), &asked) {
+                        Ok(()) => policy.call(source(asked.clone())).await,
+                        Err(err) => {
+                            yield Err(GapError::new(pages.into(), err));
+                            break;
+                        }
+                    },
+                };
+
+                let mut done = 0;
+                let mut failed = false;
+                let mut retried = false;
+
+                let cached = cache_pages::<F, _, _, PAGE_SIZE>(Arc::clone(&dir), asked.clone(), source, bounds, writer.clone(), summary.clone()).await;
+
+                for await result in detach.detach(cached.boxed()) {
+                    match result {
+                        Err(StoreError::External(err)) if policy.retries(&err, attempt) => {
+                            failed = true;
+                            retried = true;
+                            break;
+                        }
+                        Err(err) => {
+                            if err.is_source_failure() {
+                                failed = true;
+                                policy.record(dir.as_ref(), false);
+                                policy.remember(dir.as_ref(), &asked.skip(done), &err);
+                            }
+
+                            yield Err(GapError::new(asked.skip(done).into(), err));
+                        }
+                        Ok(chunk) => {
+                            done += 1;
+
+                            yield Ok(chunk);
+                        }
+                    }
+                }
+
+                if !failed {
+                    policy.record(dir.as_ref(), true);
+                }
+
+                if retried {
+                    policy.backoff::<E>(attempt).await;
+                    attempt += 1;
+
+                    // the pages before the failed one are cached already
+                    left = Some(pages.skip(done));
+                } else if asked.len() < pages.len() {
+                    attempt = 0;
+
+                    // the pages past a retried one are asked for by a call of their own
+                    left = Some(pages.skip(asked.len()));
+                }
+            }
+        }
+    }
+    .boxed()
+}
+
+/// Loads the cached runs of `pages` and fills the others from `source`, in order.
+pub(super) fn load_or_cache_pages<'a, 's, F, V, E, St, Fut, const PAGE_SIZE: Idx>(
+    dir: PagesDir,
+    pages: PagesRange<PAGE_SIZE>,
+    source: impl Fn(PagesRange<PAGE_SIZE>) -> Fut + Clone + Send + 'a,
+    bounds: SourceBounds,
+    config: &Config<PAGE_SIZE>,
+    detach: impl Detach<'s>,
+) -> impl Stream<Item = Result<Chunk<V>, GapError<E>>> + Send + 'a
+where
+    's: 'a,
+    F: PageFormat<V>,
+    V: Send + Sync + 'static,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send + 's,
+    Fut: Future<Output = St> + Send + 'a,
+{
+    let range = pages.clone().into();
+    let pages = load_or_cache_runs::<F, V, E, _, _, PAGE_SIZE>(
+        dir,
+        vec![pages],
+        source,
+        bounds,
+        config,
+        detach,
+    );
+
+    cancellable(pages, range, config.cancel.clone())
+}
+
+/// Loads the cached runs of each of `pages` and fills the others from `source`, in order.
+/// The source calls of up to `config.prefetch` upcoming uncached runs are started ahead of time,
+/// across the ranges.
+///
+/// Without prefetching, the runs are chained as they come, so loading cached runs boxes nothing:
+/// only the fills of the uncached runs are boxed, and the prefetched runs, which are kept aside.
+fn load_or_cache_runs<'a, 's, F, V, E, St, Fut, const PAGE_SIZE: Idx>(
+    dir: PagesDir,
+    pages: Vec<PagesRange<PAGE_SIZE>>,
+    source: impl Fn(PagesRange<PAGE_SIZE>) -> Fut + Clone + Send + 'a,
+    bounds: SourceBounds,
+    config: &Config<PAGE_SIZE>,
+    detach: impl Detach<'s>,
+) -> impl Stream<Item = Result<Chunk<V>, GapError<E>>> + Send + 'a
+where
+    's: 'a,
+    F: PageFormat<V>,
+    V: Send + Sync + 'static,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send + 's,
+    Fut: Future<Output = St> + Send + 'a,
+{
+    let prefetch = config.prefetch.min(config.parallelism);
+    let config = config.clone();
+    let plan = Arc::clone(&dir);
+
+    let runs = stream::iter(pages)
+        .map(move |pages| {
+            stream::once(store_pages_range::<E, PAGE_SIZE>(
+                plan.as_ref().clone(),
+                pages,
+            ))
+            .flatten()
+        })
+        .flatten()
+        .map(move |result| {
+            let dir = Arc::clone(&dir);
+            let source = source.clone();
+            let config = config.clone();
+
+            let calls = matches!(&result, Ok(store_pages) if !store_pages.cached);
+
+            let run = async move {
+                let store_pages = result?;
+
+                Ok(if store_pages.cached {
+                    Either::Left(load_pages::<F, V, E, PAGE_SIZE>(dir, store_pages.pages))
+                } else {
+                    Either::Right(
+                        fill_chunks::<F, V, E, _, _, PAGE_SIZE>(
+                            dir,
+                            store_pages.pages,
+                            source,
+                            bounds,
+                            &config,
+                            detach,
+                        )
+                        .await,
+                    )
+                })
+            };
+
+            (calls, run)
+        });
+
+    match prefetch {
+        0 => Either::Left(runs.then(|(_, run)| run).try_flatten()),
+        prefetch => {
+            let runs = runs
+                .map(|(calls, run)| (calls, run.map_ok(StreamExt::boxed).boxed()))
+                .boxed();
+
+            Either::Right(Lookahead::new(runs, prefetch))
+        }
+    }
+}
+
+/// Loads `range` from the pages under `dir`, creating it if missing,
+/// and fills the uncached pages from `source`.
+pub(super) async fn load_range<'a, 's, F, V, E, St, Fut, const PAGE_SIZE: Idx>(
+    dir: PagesDir,
+    range: IdxRange,
+    source: impl Fn(PagesRange<PAGE_SIZE>) -> Fut + Clone + Send + 'a,
+    bounds: SourceBounds,
+    config: &Config<PAGE_SIZE>,
+    detach: impl Detach<'s>,
+) -> impl Stream<Item = Result<Chunk<V>, GapError<E>>> + Send + 'a
+where
+    's: 'a,
+    F: PageFormat<V>,
+    V: Send + Sync + 'static,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send + 's,
+    Fut: Future<Output = St> + Send + 'a,
+{
+    let exists = match try_exists(dir.as_ref()).await {
+        Ok(true) => Ok(true),
+        Ok(false) => create_dir_all(dir.as_ref()).await.map(|_| false),
+        Err(err) => Err(err),
+    };
+
+    match exists {
+        Ok(true) => Either::Left(load_or_cache_pages::<F, V, E, _, _, PAGE_SIZE>(
+            dir,
+            (&range).into(),
+            source,
+            bounds,
+            config,
+            detach,
+        )),
+        Ok(false) => Either::Right(
+            fill_pages::<F, V, E, _, _, PAGE_SIZE>(
+                dir,
+                (&range).into(),
+                source,
+                bounds,
+                config,
+                detach,
+            )
+            .await,
+        ),
+        Err(err) => {
+            let err = GapError::new(range, StoreError::PathAccess(err, dir.as_ref().clone()));
+
+            Either::Right(stream::once(async { Err(err) }).boxed())
+        }
+    }
+}
+
+/// Loads each of the `ranges`, merged first, yielding the chunks tagged with the range they belong to.
+/// The ranges sharing or touching pages are loaded as one, so that a single source call fills them,
+/// and the cached and uncached pages of all of them are planned at once.
+pub(super) async fn load_ranges<'a, F, V, E, St, Fut, const PAGE_SIZE: Idx>(
+    dir: PagesDir,
+    ranges: Vec<IdxRange>,
+    source: impl Fn(PagesRange<PAGE_SIZE>) -> Fut + Clone + Send + 'a,
+    bounds: SourceBounds,
+    config: &Config<PAGE_SIZE>,
+) -> BoxStream<'a, Result<TaggedChunk<V>, GapError<E>>>
+where
+    F: PageFormat<V>,
+    V: Send + Sync + 'static,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send + 'a,
+    Fut: Future<Output = St> + Send + 'a,
+{
+    let ranges = IdxRange::merge(ranges);
+
+    let (Some(first), Some(last)) = (ranges.first(), ranges.last()) else {
+        return stream::empty().boxed();
+    };
+    let whole = IdxRange::new(first.start(), (last.end() - first.start()) as usize).unwrap();
+
+    if let Err(err) = create_dir_all(dir.as_ref()).await {
+        let err = GapError::new(whole, StoreError::PathAccess(err, dir.as_ref().clone()));
+
+        return stream::once(async { Err(err) }).boxed();
+    }
+
+    let mut spans: Vec<IdxRange> = Vec::new();
+
+    for range in &ranges {
+        match spans.last_mut() {
+            Some(span) if range.start() / PAGE_SIZE <= (span.end() - 1) / PAGE_SIZE + 1 => {
+                *span = IdxRange::new(span.start(), (range.end() - span.start()) as usize).unwrap();
+            }
+            _ => spans.push(range.clone()),
+        }
+    }
+
+    let pages = load_or_cache_runs::<F, V, E, _, _, PAGE_SIZE>(
+        dir,
+        spans.iter().map(PagesRange::from).collect(),
+        source,
+        bounds,
+        config,
+        Attached,
+    );
+
+    cancellable(pages, whole, config.cancel.clone())
+        .flat_map(move |result| {
+            stream::iter(match result {
+                Ok(chunk) => tag_chunk(chunk, &ranges).into_iter().map(Ok).collect(),
+                Err(gap) => vec![Err(gap)],
+            })
+        })
+        .boxed()
+}
+
+/// Splits `chunk` among the sorted and disjoint `ranges` it overlaps, dropping the values outside of them.
+fn tag_chunk<V>(chunk: Chunk<V>, ranges: &[IdxRange]) -> Vec<TaggedChunk<V>> {
+    let Chunk { range, mut values } = chunk;
+    let first = ranges.partition_point(|tag| tag.end() <= range.start());
+
+    let mut tagged = ranges[first..]
+        .iter()
+        .take_while(|tag| tag.start() < range.end())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .map(|tag| {
+            let part = tag.intersect(&range).unwrap();
+            let from = (part.start() - range.start()) as usize;
+
+            values.truncate(from + part.len() as usize);
+
+            let part_values = match from {
+                0 => mem::take(&mut values),
+                from => values.split_off(from),
+            };
+
+            (tag.clone(), Chunk::new(part, part_values))
+        })
+        .collect::<Vec<_>>();
+
+    tagged.reverse();
+    tagged
+}
+
+/// Loads `range` from its end: the pages come last to first, each cached one read on its own.
+/// The uncached ones are filled by runs of at most `config.max_source_range` items,
+/// or [`Config::DEFAULT_REV_RUN`] pages, each run being fetched whole before its pages are yielded.
+pub(super) async fn load_range_rev<'a, F, V, E, St, Fut, const PAGE_SIZE: Idx>(
+    dir: PagesDir,
+    range: IdxRange,
+    source: impl Fn(PagesRange<PAGE_SIZE>) -> Fut + Clone + Send + 'a,
+    bounds: SourceBounds,
+    config: &Config<PAGE_SIZE>,
+) -> PagesStream<'a, V, E>
+where
+    F: PageFormat<V>,
+    V: Send + Sync + 'static,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send + 'a,
+    Fut: Future<Output = St> + Send + 'a,
+{
+    if let Err(err) = create_dir_all(dir.as_ref()).await {
+        let err = GapError::new(range, StoreError::PathAccess(err, dir.as_ref().clone()));
+
+        return stream::once(async { Err(err) }).boxed();
+    }
+
+    let max_run = match config.max_source_range {
+        Some(max) => (max / PAGE_SIZE).clamp(1, usize::MAX as Idx) as usize,
+        None => Config::<PAGE_SIZE>::DEFAULT_REV_RUN,
+    };
+    let config = config.clone();
+
+    async_stream::stream! {
+        let mut pages = PagesRange::<PAGE_SIZE>::from(&range).pages().rev().peekable();
+
+        while let Some(page) = pages.next() {
+            if config.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                let left = IdxRange::from(&page);
+                let left = IdxRange::new(range.start(), (left.end() - range.start()) as usize).unwrap();
+
+                yield Err(GapError::new(left, StoreError::Cancelled));
+                break;
+            }
+
+            let path = page_path(dir.as_ref(), &page.page);
+
+            match try_exists(&path).await {
+                Ok(true) => {
+                    let range = IdxRange::from(&page);
+
+                    yield chunk_or_gap(range, load_page::<F, V, E, PAGE_SIZE>(path, page).await);
+                }
+                Ok(false) => {
+                    let mut run = PagesRange::from(page);
+
+                    while run.len() < max_run {
+                        let Some(previous) = pages.peek() else {
+                            break;
+                        };
+
+                        if !matches!(try_exists(page_path(dir.as_ref(), &previous.page)).await, Ok(false)) {
+                            break;
+                        }
+
+                        let previous = pages.next().unwrap();
+                        run.from = previous.page;
+                        run.first = previous.first;
+                    }
+
+                    let filled = fill_chunks::<F, V, E, _, _, PAGE_SIZE>(
+                        Arc::clone(&dir),
+                        run,
+                        source.clone(),
+                        bounds,
+                        &config,
+                        Attached,
+                    )
+                    .await
+                    .collect::<Vec<_>>()
+                    .await;
+
+                    for chunk in filled.into_iter().rev() {
+                        yield chunk;
+                    }
+                }
+                Err(err) => {
+                    let range = IdxRange::from(&page);
+
+                    yield Err(GapError::new(range, StoreError::PathAccess(err, path)));
+                }
+            }
+        }
+    }
+    .boxed()
+}
+
+/// Loads every `step`-th item of `range`, from its start: only their slots are read from the cached pages,
+/// while the uncached ones are filled whole from `source`, and cached, before their items are picked.
+pub(super) async fn load_range_strided<'a, F, V, E, St, Fut, const PAGE_SIZE: Idx>(
+    dir: PagesDir,
+    range: IdxRange,
+    step: NonZero<Idx>,
+    source: impl Fn(PagesRange<PAGE_SIZE>) -> Fut + Clone + Send + 'a,
+    bounds: SourceBounds,
+    config: &Config<PAGE_SIZE>,
+) -> BoxStream<'a, Result<V, GapError<E>>>
+where
+    F: DenseFormat<V>,
+    V: Send + Sync + 'static,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send + 'a,
+    Fut: Future<Output = St> + Send + 'a,
+{
+    if let Err(err) = create_dir_all(dir.as_ref()).await {
+        let err = GapError::new(range, StoreError::PathAccess(err, dir.as_ref().clone()));
+
+        return stream::once(async { Err(err) }).boxed();
+    }
+
+    let (start, step) = (range.start(), step.get());
+    // a range holds fewer than `usize::MAX` items, so a wider step picks its first one alone
+    let stride = usize::try_from(step).unwrap_or(usize::MAX);
+    let strided = move |from: Idx, to: Idx| {
+        let skip = (from.max(start) - start).div_ceil(step);
+
+        (start.saturating_add(skip.saturating_mul(step))..to).step_by(stride)
+    };
+
+    let runs = store_pages_range::<E, PAGE_SIZE>(dir.as_ref().clone(), (&range).into()).await;
+    let config = config.clone();
+
+    async_stream::stream! {
+        for await run in runs {
+            let store_pages = match run {
+                Ok(store_pages) => store_pages,
+                Err(gap) => {
+                    yield Err(gap);
+                    continue;
+                }
+            };
+
+            if config.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                let done = idx_bounds(&store_pages.pages).0;
+                let left = IdxRange::new(done, (range.end() - done) as usize).unwrap();
+
+                yield Err(GapError::new(left, StoreError::Cancelled));
+                break;
+            }
+
+            if store_pages.cached {
+                for page in store_pages.pages.pages() {
+                    let idxs = IdxRange::from(&page);
+                    let offsets = strided(idxs.start(), idxs.end())
+                        .map(|idx| idx % PAGE_SIZE)
+                        .collect::<Vec<_>>();
+
+                    if offsets.is_empty() {
+                        continue;
+                    }
+
+                    let path = page_path(dir.as_ref(), &page.page);
+
+                    match load_slots::<F, V, E, PAGE_SIZE>(path.clone(), page.page, offsets).await {
+                        Ok(Some(items)) => {
+                            for item in items {
+                                yield Ok(item);
+                            }
+                        }
+                        Ok(None) => {
+                            let err = io::ErrorKind::NotFound.into();
+
+                            yield Err(GapError::new(idxs, StoreError::FileOpen(err, path)));
+                        }
+                        Err(err) => yield Err(GapError::new(idxs, err)),
+                    }
+                }
+            } else {
+                let chunks = fill_chunks::<F, V, E, _, _, PAGE_SIZE>(
+                    Arc::clone(&dir),
+                    store_pages.pages,
+                    source.clone(),
+                    bounds,
+                    &config,
+                    Attached,
+                )
+                .await;
+
+                for await chunk in chunks {
+                    match chunk {
+                        Ok(chunk) => {
+                            let from = chunk.range.start();
+                            let mut picked = strided(from, chunk.range.end()).map(|idx| (idx - from) as usize).peekable();
+
+                            for (at, item) in chunk.values.into_iter().enumerate() {
+                                if picked.next_if_eq(&at).is_some() {
+                                    yield Ok(item);
+                                }
+                            }
+                        }
+                        Err(gap) => yield Err(gap),
+                    }
+                }
+            }
+        }
+    }
+    .boxed()
+}
+
+/// Summarizes the items of `range` as `S`, combining the stored summaries of its full pages
+/// and loading only the other pages, the partial ones at its edges and the full ones not yet summarized,
+/// to summarize them item by item. `None` means `range` holds no items.
+pub(super) async fn aggregate_range<'a, F, V, S, E, St, Fut, const PAGE_SIZE: Idx>(
+    dir: PagesDir,
+    range: IdxRange,
+    source: impl Fn(PagesRange<PAGE_SIZE>) -> Fut + Clone + Send + 'a,
+    bounds: SourceBounds,
+    config: &Config<PAGE_SIZE>,
+) -> Result<Option<S>, GapError<E>>
+where
+    F: PageFormat<V>,
+    V: Send + Sync + 'static,
+    S: Summary<V>,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send + 'a,
+    Fut: Future<Output = St> + Send + 'a,
+{
+    fn fold<V, S: Summary<V>>(summary: Option<S>, next: Option<S>) -> Option<S> {
+        match (summary, next) {
+            (Some(summary), Some(next)) => Some(summary.combine(next)),
+            (summary, next) => summary.or(next),
+        }
+    }
+
+    let summarized = config.summary.as_ref().filter(|summary| summary.is::<S>());
+
+    let mut summary = None;
+    let mut scan: Option<IdxRange> = None;
+
+    let mut pages = PagesRange::<PAGE_SIZE>::from(&range).pages().peekable();
+
+    while let Some(page) = pages.next() {
+        let stored = match summarized.filter(|_| page.full_fill()) {
+            Some(summarized) => read(summary_path(dir.as_ref(), &page.page, summarized))
                 .await
-                .map_err(|err| StoreError::External(err))?;
+                .ok()
+                .filter(|bytes| bytes.len() == mem::size_of::<S>())
+                .map(|bytes| bytemuck::pod_read_unaligned::<S>(&bytes)),
+            None => None,
+        };
+
+        if stored.is_none() {
+            let idxs = IdxRange::from(&page);
+
+            scan = match scan {
+                Some(scan) => IdxRange::new(scan.start(), (idxs.end() - scan.start()) as usize),
+                None => Some(idxs),
+            };
+        }
+
+        if let Some(scan) = scan.take_if(|_| stored.is_some() || pages.peek().is_none()) {
+            let chunks = load_range::<F, V, E, _, _, PAGE_SIZE>(
+                Arc::clone(&dir),
+                scan,
+                source.clone(),
+                bounds,
+                config,
+                Attached,
+            )
+            .await;
+
+            pin_mut!(chunks);
+
+            while let Some(chunk) = chunks.next().await {
+                summary = fold(summary, summarize::<V, S>(&chunk?.values));
+            }
+        }
+
+        summary = fold(summary, stored);
+    }
+
+    Ok(summary)
+}
+
+/// Loads `range` at `level` of the pyramid over the items, a chunk per level page:
+/// level 0 is loaded as usual, the pages of the levels above are read when stored,
+/// and built from the level below otherwise, as `level_page` says.
+pub(super) async fn load_level_range<'a, F, V, E, St, Fut, const PAGE_SIZE: Idx>(
+    dir: PagesDir,
+    range: IdxRange,
+    level: u32,
+    source: impl Fn(PagesRange<PAGE_SIZE>) -> Fut + Clone + Send + Sync + 'a,
+    bounds: SourceBounds,
+    config: &Config<PAGE_SIZE>,
+    reduce: Reduce<V>,
+) -> PagesStream<'a, V, E>
+where
+    F: DenseFormat<V>,
+    V: Send + Sync + 'static,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send + 'a,
+    Fut: Future<Output = St> + Send + 'a,
+{
+    if level == 0 {
+        return load_range::<F, V, E, _, _, PAGE_SIZE>(
+            dir, range, source, bounds, config, Attached,
+        )
+        .await
+        .boxed();
+    }
 
-            yield cache_page(dir.as_ref(), page, page_data).await?;
+    let config = config.clone();
+
+    async_stream::stream! {
+        for page in PagesRange::<PAGE_SIZE>::from(&range).pages() {
+            let range = IdxRange::from(&page);
+            let done = range.start();
+
+            if config.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                let left = IdxRange::new(done, (range.end() - done) as usize).unwrap();
+
+                yield Err(GapError::new(left, StoreError::Cancelled));
+                break;
+            }
+
+            match level_page::<F, _, _, _, _, _, PAGE_SIZE>(&dir, level, page, &source, bounds, &config, &reduce).await {
+                Ok(values) => match range.truncate(done + values.len() as Idx) {
+                    Some(range) => yield Ok(Chunk::new(range, values)),
+                    None => break,
+                },
+                Err(gap) => yield Err(gap),
+            }
         }
     }
+    .boxed()
 }
 
-pub(super) async fn cache_page<V, E, const PAGE_SIZE: Idx>(
+/// The values of `page` at `level`, each the reduction of `PAGE_SIZE` values of the level below.
+/// The page is read when stored, and built from the level below otherwise,
+/// being stored once the items it covers are all there, so not at the end of the data.
+fn level_page<'c, F, V, E, St, Fut, Src, const PAGE_SIZE: Idx>(
+    dir: &'c PagesDir,
+    level: u32,
+    page: PageRange<PAGE_SIZE>,
+    source: &'c Src,
+    bounds: SourceBounds,
+    config: &'c Config<PAGE_SIZE>,
+    reduce: &'c Reduce<V>,
+) -> BoxFuture<'c, Result<Vec<V>, GapError<E>>>
+where
+    F: DenseFormat<V>,
+    V: Send + Sync + 'static,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send + 'c,
+    Fut: Future<Output = St> + Send + 'c,
+    Src: Fn(PagesRange<PAGE_SIZE>) -> Fut + Clone + Send + Sync + 'c,
+{
+    async move {
+        let range = IdxRange::from(&page);
+        let level_dir = level_dir(dir.as_ref(), level);
+        let path = page_path(&level_dir, &page.page);
+
+        let stored = try_exists(&path).await.map_err(|err| {
+            GapError::new(range.clone(), StoreError::PathAccess(err, path.clone()))
+        })?;
+
+        if stored {
+            return load_page::<F, V, E, PAGE_SIZE>(path, page)
+                .await
+                .map_err(|err| GapError::new(range, err));
+        }
+
+        let below = IdxRange::new(
+            page.page * PAGE_SIZE * PAGE_SIZE,
+            (PAGE_SIZE * PAGE_SIZE) as usize,
+        )
+        .and_then(
+            |below| match level_end::<PAGE_SIZE>(config.end, level - 1) {
+                Some(end) => below.truncate(end),
+                None => Some(below),
+            },
+        );
+
+        let mut values = Vec::new();
+
+        if let Some(below) = below {
+            if level == 1 {
+                let chunks = load_range::<F, V, E, _, _, PAGE_SIZE>(
+                    Arc::clone(dir),
+                    below,
+                    source.clone(),
+                    bounds,
+                    config,
+                    Attached,
+                )
+                .await;
+
+                pin_mut!(chunks);
+
+                while let Some(chunk) = chunks.next().await {
+                    values.extend(chunk?.values);
+                }
+            } else {
+                for below in PagesRange::<PAGE_SIZE>::from(&below).pages() {
+                    values.extend(
+                        level_page::<F, _, _, _, _, _, PAGE_SIZE>(
+                            dir,
+                            level - 1,
+                            below,
+                            source,
+                            bounds,
+                            config,
+                            reduce,
+                        )
+                        .await?,
+                    );
+                }
+            }
+        }
+
+        let data = values
+            .chunks(PAGE_SIZE as usize)
+            .map(|values| reduce(values))
+            .collect::<Vec<_>>();
+
+        let covered = (page.page + 1).saturating_mul(PAGE_SIZE.saturating_pow(level + 1));
+
+        let mut data = if config.end.is_none_or(|end| end >= covered) {
+            create_dir_all(&level_dir).await.map_err(|err| {
+                GapError::new(
+                    range.clone(),
+                    StoreError::PathAccess(err, level_dir.clone().into()),
+                )
+            })?;
+
+            let whole = PageRange::<PAGE_SIZE>::new(page.page, 0, PAGE_SIZE - 1);
+
+            cache_page::<F, _, _, PAGE_SIZE>(&level_dir, whole, data, None, None)
+                .await
+                .map_err(|err| GapError::new(range, err))?
+        } else {
+            data
+        };
+
+        let first = (page.first as usize).min(data.len());
+        let last = (page.last as usize + 1).min(data.len());
+
+        Ok(data.drain(first..last).collect())
+    }
+    .boxed()
+}
+
+/// The end of the data at `level`, each value of which covers `PAGE_SIZE^level` items.
+pub(super) fn level_end<const PAGE_SIZE: Idx>(end: Option<Idx>, level: u32) -> Option<Idx> {
+    end.map(|end| end.div_ceil(PAGE_SIZE.saturating_pow(level)))
+}
+
+/// The values of an infallible load, which has no error to yield: it ends at the first
+/// cancelled or timed-out call, silently, and panics on the other store errors.
+pub(super) fn infallible<'a, T: Send + 'a>(
+    results: impl Stream<Item = Result<T, GapError<()>>> + Send + 'a,
+) -> impl Stream<Item = T> + Send + 'a {
+    results
+        .take_while(|result| future::ready(!matches!(result, Err(gap) if gap.error.interrupts())))
+        .map(|result| match result {
+            Ok(item) => item,
+            Err(err) => panic!("{}", err),
+        })
+}
+
+/// The source errors of a load yielding no store error: it ends at the first call that was cancelled,
+/// timed out or not made at all, which has no source error to be reported as, and panics on the other store errors.
+pub(super) fn external<'a, T: Send + 'a, E: Send + 'a>(
+    results: impl Stream<Item = Result<T, StoreError<E>>> + Send + 'a,
+) -> impl Stream<Item = Result<T, E>> + Send + 'a {
+    results
+        .take_while(|result| {
+            future::ready(!matches!(result, Err(err) if err.interrupts() || err.refuses()))
+        })
+        .map(|result| match result {
+            Ok(item) => Ok(item),
+            Err(StoreError::External(err)) => Err(err),
+            Err(err) => panic!("{}", err),
+        })
+}
+
+/// A copy of `config` without the timeouts, for the loads that can't end early.
+pub(super) fn untimed<const PAGE_SIZE: Idx>(config: &Config<PAGE_SIZE>) -> Config<PAGE_SIZE> {
+    Config {
+        call_timeout: None,
+        item_timeout: None,
+        ..config.clone()
+    }
+}
+
+/// Ends `pages` once `cancel` is cancelled, reporting the rest of `range` as a gap.
+pub(super) fn cancellable<'a, V, E>(
+    pages: impl Stream<Item = Result<Chunk<V>, GapError<E>>> + Send + 'a,
+    range: IdxRange,
+    cancel: Option<CancelToken>,
+) -> impl Stream<Item = Result<Chunk<V>, GapError<E>>> + Send + 'a
+where
+    V: Send + 'a,
+    E: Send + 'a,
+{
+    let Some(cancel) = cancel else {
+        return Either::Left(pages);
+    };
+
+    let end = range.start() + range.len();
+
+    Either::Right(async_stream::stream! {
+        let mut next = range.start();
+        pin_mut!(pages);
+
+        loop {
+            let item = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => {
+                    if let Some(range) = IdxRange::new(next, (end - next) as usize) {
+                        yield Err(GapError::new(range, StoreError::Cancelled));
+                    }
+
+                    break;
+                }
+                item = pages.next() => item,
+            };
+
+            match item {
+                Some(item) => {
+                    let done = match &item {
+                        Ok(chunk) => &chunk.range,
+                        Err(gap) => &gap.range,
+                    };
+                    next = done.start() + done.len();
+
+                    yield item;
+                }
+                None => break,
+            }
+        }
+    })
+}
+
+/// The items of a call from its start: a source called with a range without a start, as `..`,
+/// yields from the first item, so the items before the call are dropped.
+fn from_call_start<F, V, E>(
+    source: impl Stream<Item = Result<V, E>>,
+    start: Idx,
+    bounds: SourceBounds,
+) -> impl Stream<Item = Result<V, E>>
+where
+    F: PageFormat<V>,
+{
+    let mut skipped: Idx = 0;
+
+    source.skip_while(move |item| {
+        let before = bounds.from_first
+            && match item {
+                Ok(item) => match F::index(item) {
+                    Some(idx) => idx < start,
+                    None => {
+                        skipped += 1;
+                        skipped <= start
+                    }
+                },
+                Err(_) => false,
+            };
+
+        future::ready(before)
+    })
+}
+
+/// Checks that the index `idx` given by the source follows `last`, and that it is within `call`,
+/// or past it for an open range, whose extra items are dropped.
+fn check_index<E, const PAGE_SIZE: Idx>(
+    page: &PageRange<PAGE_SIZE>,
+    call: &IdxRange,
+    bounds: SourceBounds,
+    last: Option<Idx>,
+    idx: Idx,
+) -> Result<(), StoreError<E>> {
+    let ordered = last.is_none_or(|last| last < idx);
+    let within = call.start() <= idx && (bounds.open || idx < call.end());
+
+    if ordered && within {
+        Ok(())
+    } else {
+        Err(StoreError::SourceIndex(page.page, idx))
+    }
+}
+
+fn check_page_len<F, V, E, const PAGE_SIZE: Idx>(
+    page: &PageRange<PAGE_SIZE>,
+    taken: usize,
+    extra: usize,
+) -> Result<(), StoreError<E>>
+where
+    F: PageFormat<V>,
+{
+    let expected = F::expected(page, taken);
+
+    if taken == expected && extra == 0 {
+        Ok(())
+    } else {
+        Err(StoreError::SourceLength(page.page, expected, taken + extra))
+    }
+}
+
+/// Writes `page` to the cache, or hands it to `writer` to write it in the background.
+/// The summary of a full page is written after it, in the same background job with a `writer`,
+/// so a failed page write leaves no summary behind; a page whose summary is missing is summarized
+/// from its items. The level pages built on top of it are removed once it is written or handed to `writer`.
+pub(super) async fn cache_page<F, V, E, const PAGE_SIZE: Idx>(
     dir: impl AsRef<Path>,
     page: PageRange<PAGE_SIZE>,
     data: Vec<V>,
+    writer: Option<&WriteBehind>,
+    summary: Option<&PageSummary>,
 ) -> Result<Vec<V>, StoreError<E>>
 where
-    V: NoUninit + Send,
+    F: PageFormat<V>,
+    V: Send + 'static,
     E: Send + 'static,
 {
-    use std::io::Write;
+    if page.full_fill() {
+        let page_idx = page.page;
+        let path = page_path(dir.as_ref(), &page_idx);
+        let summarized = summary
+            .and_then(|summary| summary.summarize(&data[..]).map(|bytes| (summary, bytes)))
+            .map(|(summary, bytes)| (summary_path(dir.as_ref(), &page_idx, summary), bytes));
 
-    use bytemuck::cast_slice;
+        if let Some(writer) = writer {
+            let mut bytes = Vec::new();
 
-    if page.full_fill() {
-        let path = page_path(dir.as_ref(), &page.page);
+            F::write(&mut bytes, &page, &data[..])
+                .map_err(|_| StoreError::PageWrite(path.clone()))?;
+
+            writer
+                .write(iter::once((path, bytes)).chain(summarized).collect())
+                .await;
+            invalidate_levels::<E, PAGE_SIZE>(dir.as_ref(), page_idx).await?;
+
+            return Ok(data);
+        }
 
         let join = task::spawn_blocking(move || {
             let mut file =
                 File::create(&path).map_err(|err| StoreError::FileCreation(err, path.clone()))?;
 
-            file.write_all(cast_slice(&data[..]))
+            F::write(&mut file, &page, &data[..])
                 .map_err(|_| StoreError::PageWrite(path.clone()))?;
 
+            if let Some((summary_path, summary)) = summarized {
+                fs::write(&summary_path, summary)
+                    .map_err(|err| StoreError::FileCreation(err, summary_path.clone()))?;
+            }
+
             Ok::<_, StoreError<E>>(data)
         });
 
-        match join.await {
+        let data = match join.await {
             Err(err) => {
                 panic!("{}", err)
             }
-            Ok(data) => data,
-        }
+            Ok(data) => data?,
+        };
+
+        invalidate_levels::<E, PAGE_SIZE>(dir.as_ref(), page_idx).await?;
+
+        Ok(data)
     } else {
         Ok(data)
     }
 }
 
+/// Removes the level pages built on top of `page`, so that they are built again from it.
+/// A missing level dir means no level above was ever built.
+async fn invalidate_levels<E, const PAGE_SIZE: Idx>(
+    dir: &Path,
+    page: Idx,
+) -> Result<(), StoreError<E>> {
+    let mut level = 1;
+    let mut level_page = page;
+
+    loop {
+        let level_dir = level_dir(dir, level);
+
+        if !try_exists(&level_dir)
+            .await
+            .map_err(|err| StoreError::PathAccess(err, level_dir.clone().into()))?
+        {
+            return Ok(());
+        }
+
+        level_page /= PAGE_SIZE;
+
+        let path = page_path(&level_dir, &level_page);
+
+        match remove_file(&path).await {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(StoreError::PathAccess(err, path));
+            }
+            _ => level += 1,
+        }
+    }
+}
+
+/// Loads `page` from the cache or fetches it whole, retrying as `policy` says,
+/// and caches it before keeping the requested part.
+/// A short page is only accepted at the end of the data, and is not cached.
+pub(super) async fn load_or_fetch_page<V, E, St, Fut, const PAGE_SIZE: Idx>(
+    dir: PagesDir,
+    page: PageRange<PAGE_SIZE>,
+    source: impl Fn(Idx) -> Fut,
+    end_of_data: bool,
+    policy: SourcePolicy,
+    writer: Option<Arc<WriteBehind>>,
+    summary: Option<PageSummary>,
+) -> Result<Chunk<V>, GapError<E>>
+where
+    V: NoUninit + AnyBitPattern + Send,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send,
+    Fut: Future<Output = St>,
+{
+    let range = IdxRange::from(&page);
+
+    chunk_or_gap(
+        range,
+        fetch_page(dir, page, source, end_of_data, policy, writer, summary).await,
+    )
+}
+
+async fn fetch_page<V, E, St, Fut, const PAGE_SIZE: Idx>(
+    dir: PagesDir,
+    page: PageRange<PAGE_SIZE>,
+    source: impl Fn(Idx) -> Fut,
+    end_of_data: bool,
+    policy: SourcePolicy,
+    writer: Option<Arc<WriteBehind>>,
+    summary: Option<PageSummary>,
+) -> Result<Vec<V>, StoreError<E>>
+where
+    V: NoUninit + AnyBitPattern + Send,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send,
+    Fut: Future<Output = St>,
+{
+    let path = page_path(dir.as_ref(), &page.page);
+
+    if try_exists(&path)
+        .await
+        .map_err(|err| StoreError::PathAccess(err, path.clone()))?
+    {
+        return load_page::<Plain, _, _, PAGE_SIZE>(path, page).await;
+    }
+
+    let pages = PageRange::<PAGE_SIZE>::new(page.page, 0, PAGE_SIZE - 1).into();
+    let mut attempt = 0;
+
+    let data = loop {
+        policy.admit(dir.as_ref(), &pages)?;
+
+        match policy
+            .call(source(page.page))
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+        {
+            Ok(data) => {
+                policy.record(dir.as_ref(), true);
+                break data;
+            }
+            Err(StoreError::External(err)) if policy.retries(&err, attempt) => {
+                policy.backoff::<E>(attempt).await;
+                attempt += 1;
+            }
+            Err(err) => {
+                policy.record(dir.as_ref(), false);
+                policy.remember(dir.as_ref(), &pages, &err);
+
+                return Err(err);
+            }
+        }
+    };
+
+    let whole = PageRange::<PAGE_SIZE>::new(page.page, 0, PAGE_SIZE - 1);
+    let (first, last) = (page.first as usize, page.last as usize);
+
+    let mut data = if end_of_data && data.len() > last && data.len() < whole.len() {
+        data
+    } else {
+        check_page_len::<Plain, V, E, PAGE_SIZE>(&whole, data.len(), 0)?;
+
+        cache_page::<Plain, _, _, PAGE_SIZE>(
+            dir.as_ref(),
+            whole,
+            data,
+            writer.as_deref(),
+            summary.as_ref(),
+        )
+        .await?
+    };
+
+    Ok(data.drain(first..=last).collect())
+}
+
+/// Loads the items at `idxs`, in order, `None` standing for those past the end of the data.
+/// Each cached page is opened once and only the requested slots are read from it,
+/// the items of an uncached page are asked to `source` in a single call, without caching them.
+pub(super) async fn get_items<F, V, E, St, Fut, const PAGE_SIZE: Idx>(
+    dir: PagesDir,
+    idxs: &[Idx],
+    source: impl Fn(IdxRange) -> Fut,
+    bounds: SourceBounds,
+    config: &Config<PAGE_SIZE>,
+) -> Result<Vec<Option<V>>, StoreError<E>>
+where
+    F: DenseFormat<V>,
+    V: Clone + Send + 'static,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send,
+    Fut: Future<Output = St>,
+{
+    let mut pages = BTreeMap::<Idx, Vec<usize>>::new();
+
+    for (at, idx) in idxs.iter().enumerate() {
+        if config.end.is_none_or(|end| *idx < end) {
+            pages.entry(idx / PAGE_SIZE).or_default().push(at);
+        }
+    }
+
+    let policy = SourcePolicy::new(config);
+    let mut items = iter::repeat_with(|| None)
+        .take(idxs.len())
+        .collect::<Vec<_>>();
+
+    for (page, ats) in pages {
+        let offsets = ats.iter().map(|at| idxs[*at] % PAGE_SIZE).collect();
+
+        if let Some(slots) =
+            load_slots::<F, V, E, PAGE_SIZE>(page_path(dir.as_ref(), &page), page, offsets).await?
+        {
+            for (at, item) in ats.into_iter().zip(slots) {
+                items[at] = Some(item);
+            }
+
+            continue;
+        }
+
+        let first = ats.iter().map(|at| idxs[*at]).min().unwrap();
+        let last = ats.iter().map(|at| idxs[*at]).max().unwrap();
+        let len = (last - first + 1) as usize;
+
+        let pages = PageRange::<PAGE_SIZE>::new(page, first % PAGE_SIZE, last % PAGE_SIZE).into();
+        policy.admit(dir.as_ref(), &pages)?;
+
+        let fetched = from_call_start::<F, _, _>(
+            policy
+                .call(source(IdxRange::new(first, len).unwrap()))
+                .await,
+            first,
+            bounds,
+        )
+        .take(len)
+        .try_collect::<Vec<_>>()
+        .await
+        .and_then(|fetched| match fetched.len() {
+            fetched_len if fetched_len == len => Ok(fetched),
+            fetched_len => Err(StoreError::SourceLength(page, len, fetched_len)),
+        });
+
+        match fetched {
+            Ok(fetched) => {
+                policy.record(dir.as_ref(), true);
+
+                for at in ats {
+                    items[at] = Some(fetched[(idxs[at] - first) as usize].clone());
+                }
+            }
+            Err(err) => {
+                if err.is_source_failure() {
+                    policy.record(dir.as_ref(), false);
+                    policy.remember(dir.as_ref(), &pages, &err);
+                }
+
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(items)
+}
+
+/// Loads the item at `idx`, `None` standing for one past the end of the data.
+/// Only its slot is read from its cached page, and it is asked to `source` alone when the page is not cached,
+/// without caching it.
+pub(super) async fn get_item<F, V, E, St, Fut, const PAGE_SIZE: Idx>(
+    dir: PagesDir,
+    idx: Idx,
+    source: impl FnOnce(IdxRange) -> Fut,
+    bounds: SourceBounds,
+    config: &Config<PAGE_SIZE>,
+) -> Result<Option<V>, StoreError<E>>
+where
+    F: DenseFormat<V>,
+    V: Send + 'static,
+    E: Send + 'static,
+    St: Stream<Item = Result<V, E>> + Send,
+    Fut: Future<Output = St>,
+{
+    if config.end.is_some_and(|end| idx >= end) {
+        return Ok(None);
+    }
+
+    let (page, offset) = (idx / PAGE_SIZE, idx % PAGE_SIZE);
+
+    if let Some(mut slots) =
+        load_slots::<F, V, E, PAGE_SIZE>(page_path(dir.as_ref(), &page), page, vec![offset]).await?
+    {
+        return Ok(slots.pop());
+    }
+
+    let pages = PageRange::<PAGE_SIZE>::new(page, offset, offset).into();
+    let policy = SourcePolicy::new(config);
+    policy.admit(dir.as_ref(), &pages)?;
+
+    let fetched = from_call_start::<F, _, _>(
+        policy.call(source(IdxRange::new(idx, 1).unwrap())).await,
+        idx,
+        bounds,
+    )
+    .next()
+    .await
+    .unwrap_or(Err(StoreError::SourceLength(page, 1, 0)));
+
+    match fetched {
+        Ok(item) => {
+            policy.record(dir.as_ref(), true);
+
+            Ok(Some(item))
+        }
+        Err(err) => {
+            if err.is_source_failure() {
+                policy.record(dir.as_ref(), false);
+                policy.remember(dir.as_ref(), &pages, &err);
+            }
+
+            Err(err)
+        }
+    }
+}
+
+/// Reads the items at `offsets` of `page`, `None` meaning the page is not cached.
+async fn load_slots<F, V, E, const PAGE_SIZE: Idx>(
+    page_path: PagePath,
+    page: Idx,
+    offsets: Vec<Idx>,
+) -> Result<Option<Vec<V>>, StoreError<E>>
+where
+    F: DenseFormat<V>,
+    V: Send + 'static,
+    E: Send + 'static,
+{
+    let join = task::spawn_blocking(move || {
+        let mut file = match File::open(page_path.as_ref()) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(StoreError::FileOpen(err, page_path.clone())),
+        };
+
+        offsets
+            .into_iter()
+            .map(|offset| {
+                let slot = PageRange::<PAGE_SIZE>::new(page, offset, offset);
+
+                F::read(&mut file, &slot)
+                    .ok()
+                    .and_then(|items| items.into_iter().next())
+                    .ok_or_else(|| StoreError::PageRead(page_path.clone()))
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    });
+
+    match join.await {
+        Err(err) => {
+            panic!("{}", err)
+        }
+        Ok(slots) => slots,
+    }
+}
+
+fn idx_bounds<const PAGE_SIZE: Idx>(pages: &PagesRange<PAGE_SIZE>) -> (Idx, Idx) {
+    (
+        pages.from * PAGE_SIZE + pages.first,
+        pages.to * PAGE_SIZE + pages.last,
+    )
+}
+
+/// Resolves `r` against the end of the data, the one the source gives for the key, `end`, or else `config.end`.
+/// `Ok(None)` means there is nothing left to load, and `Err` gives back an open `r` when the end of the data is unknown.
+pub(super) fn resolve_range<R: SourceRange, const PAGE_SIZE: Idx>(
+    r: R,
+    end: Option<Idx>,
+    config: &Config<PAGE_SIZE>,
+) -> Result<Option<IdxRange>, R> {
+    let end = end.or(config.end);
+
+    match r.resolve(end) {
+        Ok(range) => Ok(match end {
+            Some(end) => range.truncate(end),
+            None => Some(range),
+        }),
+        Err(r) if R::OPEN && end.is_none() => Err(r),
+        // an empty range, or one starting past the end of the data
+        Err(_) => Ok(None),
+    }
+}
+
 pub(super) fn page_path(dir: impl AsRef<Path>, page: &Idx) -> PagePath {
     dir.as_ref().join(format!("{}", page)).into()
 }
 
-pub(super) fn pages_dir<K: Hash, V: 'static, const PAGE_SIZE: Idx>(
+pub(super) fn summary_path(dir: impl AsRef<Path>, page: &Idx, summary: &PageSummary) -> PagePath {
+    dir.as_ref()
+        .join(format!("{}.{}.summary", page, summary.name()))
+        .into()
+}
+
+pub(super) fn level_dir(dir: impl AsRef<Path>, level: u32) -> PathBuf {
+    dir.as_ref().join(format!("level_{}", level))
+}
+
+pub(super) fn pages_dir<F: PageFormat<V>, K: Hash, V: 'static, const PAGE_SIZE: Idx>(
     k: K,
-    config: &TypedConfig<V, PAGE_SIZE>,
+    config: &TypedConfig<V, PAGE_SIZE, F>,
 ) -> PagesDir {
     use std::any::TypeId;
     use std::mem::size_of;
@@ -199,10 +1949,16 @@ pub(super) fn pages_dir<K: Hash, V: 'static, const PAGE_SIZE: Idx>(
 
     let mut hasher = STATE.build_hasher();
 
+    // only the formats with a kind hash it, so just `Plain` pages keep the directory they had
+    // before the formats, and a cache of those is still read after upgrading
     TypeId::of::<TypedConfig<V, PAGE_SIZE>>().hash(&mut hasher);
 
     size_of::<V>().hash(&mut hasher);
 
+    if let Some(kind) = F::KIND {
+        kind.hash(&mut hasher);
+    }
+
     k.hash(&mut hasher);
 
     Arc::new(config.root.join(format!("{}", hasher.finish())).into())
@@ -210,10 +1966,10 @@ pub(super) fn pages_dir<K: Hash, V: 'static, const PAGE_SIZE: Idx>(
 
 #[cfg(test)]
 mod tests {
-    use std::ops::RangeInclusive;
+    use std::ops::{Range, RangeInclusive};
     use std::path::PathBuf;
 
-    use futures::stream::{self, BoxStream};
+    use futures::stream::{self, BoxStream, TryStreamExt};
 
     use tokio::fs::{create_dir, remove_dir_all, try_exists};
 
@@ -238,18 +1994,20 @@ mod tests {
 
         let idx_range = IdxRange::try_from(range.clone()).unwrap();
 
-        let values = cache_pages::<_, PAGE_SIZE>(
+        let values = cache_pages::<Plain, _, (), PAGE_SIZE>(
             dir.clone(),
             PagesRange::<PAGE_SIZE>::from(&idx_range),
-            source(&(), range.clone()).await,
+            source(&(), range.clone()).await.map(Ok),
+            SourceBounds::new::<RangeInclusive<Idx>, PAGE_SIZE>(&Config::new()),
+            None,
+            None,
         )
         .await
         .try_collect::<Vec<_>>()
         .await
         .unwrap()
         .into_iter()
-        .map(IntoIterator::into_iter)
-        .flatten()
+        .flat_map(|chunk| chunk.values)
         .collect::<Vec<_>>();
 
         assert_ne!(try_exists(dir.join("0")).await.unwrap(), true);
@@ -278,25 +2036,27 @@ mod tests {
 
         let idx_range = IdxRange::try_from(range.clone()).unwrap();
 
-        let values = cache_pages::<_, PAGE_SIZE>(
+        let values = cache_pages::<Plain, _, (), PAGE_SIZE>(
             dir.clone(),
             PagesRange::<PAGE_SIZE>::from(&idx_range),
-            source(&(), range.clone()).await,
+            source(&(), range.clone()).await.map(Ok),
+            SourceBounds::new::<RangeInclusive<Idx>, PAGE_SIZE>(&Config::new()),
+            None,
+            None,
         )
         .await
         .try_collect::<Vec<_>>()
         .await
         .unwrap()
         .into_iter()
-        .map(IntoIterator::into_iter)
-        .flatten()
+        .flat_map(|chunk| chunk.values)
         .collect::<Vec<_>>();
 
         assert_eq!(range.clone().collect::<Vec<_>>(), values);
 
         let offset = 512;
         let cached_range = IdxRange::try_from(offset..(2 as Idx).pow(11)).unwrap();
-        let cached = load_pages::<Idx, (), PAGE_SIZE>(
+        let cached = load_pages::<Plain, Idx, (), PAGE_SIZE>(
             Arc::clone(&dir),
             PagesRange::<PAGE_SIZE>::from(&cached_range),
         )
@@ -304,8 +2064,7 @@ mod tests {
         .await
         .unwrap()
         .into_iter()
-        .map(IntoIterator::into_iter)
-        .flatten()
+        .flat_map(|chunk| chunk.values)
         .collect::<Vec<_>>();
 
         assert_eq!(
@@ -315,4 +2074,148 @@ mod tests {
 
         remove_dir_all(dir.as_ref()).await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_cache_pages_length() {
+        const PAGE_SIZE: Idx = 1024;
+
+        let dir: Arc<Cow<'static, _>> =
+            Arc::new(PathBuf::from(format!("{}", rand::random::<u128>())).into());
+        create_dir(dir.as_ref()).await.unwrap();
+
+        let idx_range = IdxRange::try_from(0..3 * PAGE_SIZE).unwrap();
+
+        let short = cache_pages::<Plain, _, (), PAGE_SIZE>(
+            dir.clone(),
+            PagesRange::<PAGE_SIZE>::from(&idx_range),
+            stream::iter(0..2 * PAGE_SIZE + 10).map(Ok),
+            SourceBounds::new::<Range<Idx>, PAGE_SIZE>(&Config::new()),
+            None,
+            None,
+        )
+        .await
+        .collect::<Vec<_>>()
+        .await;
+
+        assert_eq!(short.len(), 3);
+        assert!(matches!(
+            short[2],
+            Err(StoreError::SourceLength(2, 1024, 10))
+        ));
+        assert!(!try_exists(dir.join("2")).await.unwrap());
+
+        let long = cache_pages::<Plain, _, (), PAGE_SIZE>(
+            dir.clone(),
+            PagesRange::<PAGE_SIZE>::from(&idx_range),
+            stream::iter(0..3 * PAGE_SIZE + 10).map(Ok),
+            SourceBounds::new::<Range<Idx>, PAGE_SIZE>(&Config::new()),
+            None,
+            None,
+        )
+        .await
+        .collect::<Vec<_>>()
+        .await;
+
+        assert_eq!(long.len(), 3);
+        assert!(matches!(
+            long[2],
+            Err(StoreError::SourceLength(2, 1024, 1025))
+        ));
+        assert!(!try_exists(dir.join("2")).await.unwrap());
+
+        let endless = cache_pages::<Plain, _, (), PAGE_SIZE>(
+            dir.clone(),
+            PagesRange::<PAGE_SIZE>::from(&idx_range),
+            stream::iter(0..).map(Ok),
+            SourceBounds::new::<Range<Idx>, PAGE_SIZE>(&Config::new()),
+            None,
+            None,
+        )
+        .await
+        .collect::<Vec<_>>()
+        .await;
+
+        assert_eq!(endless.len(), 3);
+        assert!(matches!(
+            endless[2],
+            Err(StoreError::SourceLength(2, 1024, 1025))
+        ));
+
+        remove_dir_all(dir.as_ref()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cache_page_summary() {
+        use bytemuck::{Pod, Zeroable};
+
+        use crate::Summary;
+
+        #[derive(Clone, Copy, Pod, Zeroable)]
+        #[repr(C)]
+        struct Count(u64);
+
+        impl Summary<Idx> for Count {
+            fn of(_item: &Idx) -> Self {
+                Count(1)
+            }
+
+            fn combine(self, next: Self) -> Self {
+                Count(self.0 + next.0)
+            }
+        }
+
+        const PAGE_SIZE: Idx = 4;
+
+        let dir = PathBuf::from(format!("{}", rand::random::<u128>()));
+        create_dir(&dir).await.unwrap();
+
+        let summary = PageSummary::new::<Idx, Count>();
+        let cache = |page: Idx| {
+            cache_page::<Plain, _, (), PAGE_SIZE>(
+                &dir,
+                PageRange::new(page, 0, PAGE_SIZE - 1),
+                (0..PAGE_SIZE).collect(),
+                None,
+                Some(&summary),
+            )
+        };
+
+        cache(0).await.unwrap();
+
+        assert!(try_exists(page_path(&dir, &0)).await.unwrap());
+        assert!(try_exists(summary_path(&dir, &0, &summary)).await.unwrap());
+
+        // the page path taken by a dir fails the page write, which leaves no summary behind
+        create_dir(page_path(&dir, &1)).await.unwrap();
+
+        assert!(cache(1).await.is_err());
+        assert!(!try_exists(summary_path(&dir, &1, &summary)).await.unwrap());
+
+        remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_items_length() {
+        const PAGE_SIZE: Idx = 1024;
+
+        let dir: PagesDir = Arc::new(PathBuf::from(format!("{}", rand::random::<u128>())).into());
+        create_dir(dir.as_ref()).await.unwrap();
+
+        let short = get_items::<Plain, Idx, (), _, _, PAGE_SIZE>(
+            dir.clone(),
+            &[10, 20],
+            |range| {
+                let start = range.start();
+
+                async move { stream::iter(start..start + 5).map(Ok) }
+            },
+            SourceBounds::new::<Range<Idx>, PAGE_SIZE>(&Config::new()),
+            &Config::new(),
+        )
+        .await;
+
+        assert!(matches!(short, Err(StoreError::SourceLength(0, 11, 5))));
+
+        remove_dir_all(dir.as_ref()).await.unwrap();
+    }
 }
@@ -0,0 +1,307 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem::size_of;
+
+use bytemuck::{cast_slice, cast_slice_mut, AnyBitPattern, NoUninit};
+
+use crate::pages::PageRange;
+use crate::{Idx, PageCodec};
+
+/// How the items of a page are laid out in its file.
+///
+/// The format of a store is picked through [`StoredAs`], so it is usually inferred from the item type,
+/// and it may be pinned by the type of the config, e.g. `TypedConfig<String, 1024, Encoded>`.
+pub trait PageFormat<V>: Send + Sync + 'static {
+    /// Tells the caches of the formats apart, `None` keeping the directory of plain pages.
+    const KIND: Option<&'static str>;
+
+    /// Reads the items of `page`, from `page.first` to `page.last`, out of the file of the whole page.
+    fn read<const PAGE_SIZE: Idx>(
+        file: &mut File,
+        page: &PageRange<PAGE_SIZE>,
+    ) -> io::Result<Vec<V>>;
+
+    /// Writes `data`, the items of the whole `page`.
+    fn write<const PAGE_SIZE: Idx>(
+        file: &mut impl Write,
+        page: &PageRange<PAGE_SIZE>,
+        data: &[V],
+    ) -> io::Result<()>;
+
+    /// Whether the next source item belongs to `page`, which already holds `taken` items.
+    fn fits<const PAGE_SIZE: Idx>(page: &PageRange<PAGE_SIZE>, _item: &V, taken: usize) -> bool {
+        taken < page.len()
+    }
+
+    /// How many source items `page` must be made of, given it took `taken` of them.
+    fn expected<const PAGE_SIZE: Idx>(page: &PageRange<PAGE_SIZE>, _taken: usize) -> usize {
+        page.len()
+    }
+
+    /// The index the source gave along with the item, for the formats whose items carry it,
+    /// which must then be increasing and within the requested range.
+    fn index(_item: &V) -> Option<Idx> {
+        None
+    }
+}
+
+/// A format holding an item at every index of a page, so that its slots can be read on their own
+/// and the values of its levels reduced from `PAGE_SIZE` consecutive items.
+pub trait DenseFormat<V>: PageFormat<V> {}
+
+/// Picks the format the items are stored in, e.g. [`Plain`] for the `Pod` ones.
+pub trait StoredAs<F: PageFormat<Self>>: Sized {}
+
+/// Items are stored as their raw bytes, one after another.
+pub struct Plain;
+
+impl<V> PageFormat<V> for Plain
+where
+    V: NoUninit + AnyBitPattern,
+{
+    const KIND: Option<&'static str> = None;
+
+    fn read<const PAGE_SIZE: Idx>(
+        file: &mut File,
+        page: &PageRange<PAGE_SIZE>,
+    ) -> io::Result<Vec<V>> {
+        file.seek(SeekFrom::Start(page.first as u64 * size_of::<V>() as u64))?;
+
+        let mut buf = vec![V::zeroed(); page.len()];
+
+        file.read_exact(cast_slice_mut(&mut buf))?;
+
+        Ok(buf)
+    }
+
+    fn write<const PAGE_SIZE: Idx>(
+        file: &mut impl Write,
+        _page: &PageRange<PAGE_SIZE>,
+        data: &[V],
+    ) -> io::Result<()> {
+        file.write_all(cast_slice(data))
+    }
+}
+
+impl<V> DenseFormat<V> for Plain where V: NoUninit + AnyBitPattern {}
+
+impl<V> StoredAs<Plain> for V where V: NoUninit + AnyBitPattern {}
+
+/// Items are stored through [`PageCodec`] behind an offset table:
+/// `count`, `count + 1` payload offsets and the payload, all offsets being little-endian `u64`.
+pub struct Encoded;
+
+impl<V> PageFormat<V> for Encoded
+where
+    V: PageCodec,
+{
+    const KIND: Option<&'static str> = Some("codec");
+
+    fn read<const PAGE_SIZE: Idx>(
+        file: &mut File,
+        page: &PageRange<PAGE_SIZE>,
+    ) -> io::Result<Vec<V>> {
+        let (first, len) = (page.first as usize, page.len());
+        file.rewind()?;
+        let count = read_u64s(file, 1)?[0] as usize;
+
+        if first + len > count {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let header = ((count + 2) * size_of::<u64>()) as u64;
+
+        file.seek(SeekFrom::Start(((first + 1) * size_of::<u64>()) as u64))?;
+        let offsets = read_u64s(file, len + 1)?;
+
+        file.seek(SeekFrom::Start(header + offsets[0]))?;
+        let mut payload = vec![0u8; (offsets[len] - offsets[0]) as usize];
+        file.read_exact(&mut payload)?;
+
+        offsets
+            .windows(2)
+            .map(|bounds| {
+                let start = (bounds[0] - offsets[0]) as usize;
+                let end = (bounds[1] - offsets[0]) as usize;
+
+                payload
+                    .get(start..end)
+                    .and_then(V::decode)
+                    .ok_or_else(|| io::ErrorKind::InvalidData.into())
+            })
+            .collect()
+    }
+
+    fn write<const PAGE_SIZE: Idx>(
+        file: &mut impl Write,
+        _page: &PageRange<PAGE_SIZE>,
+        data: &[V],
+    ) -> io::Result<()> {
+        let mut payload = vec![];
+        let mut header = Vec::with_capacity((data.len() + 2) * size_of::<u64>());
+
+        header.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        header.extend_from_slice(&0u64.to_le_bytes());
+
+        for item in data {
+            item.encode(&mut payload);
+            header.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        }
+
+        file.write_all(&header)?;
+        file.write_all(&payload)
+    }
+}
+
+impl<V> DenseFormat<V> for Encoded where V: PageCodec {}
+
+impl<V> StoredAs<Encoded> for V where V: PageCodec {}
+
+/// Optional items are stored as a validity bitmap followed by a [`Plain`] column,
+/// missing items being zeroed: `count` as little-endian `u64`, the bitmap and the values.
+pub struct Nullable;
+
+impl<V> PageFormat<Option<V>> for Nullable
+where
+    V: NoUninit + AnyBitPattern,
+{
+    const KIND: Option<&'static str> = Some("nullable");
+
+    fn read<const PAGE_SIZE: Idx>(
+        file: &mut File,
+        page: &PageRange<PAGE_SIZE>,
+    ) -> io::Result<Vec<Option<V>>> {
+        let (first, len) = (page.first as usize, page.len());
+        file.rewind()?;
+        let count = read_u64s(file, 1)?[0] as usize;
+
+        if first + len > count {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let header = size_of::<u64>() + count.div_ceil(8);
+
+        let mut bitmap = vec![0u8; (first + len - 1) / 8 + 1 - first / 8];
+        file.seek(SeekFrom::Start((size_of::<u64>() + first / 8) as u64))?;
+        file.read_exact(&mut bitmap)?;
+
+        let mut values = vec![V::zeroed(); len];
+        file.seek(SeekFrom::Start((header + first * size_of::<V>()) as u64))?;
+        file.read_exact(cast_slice_mut(&mut values))?;
+
+        Ok(values
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let bit = first % 8 + i;
+
+                (bitmap[bit / 8] & (1 << (bit % 8)) != 0).then_some(value)
+            })
+            .collect())
+    }
+
+    fn write<const PAGE_SIZE: Idx>(
+        file: &mut impl Write,
+        page: &PageRange<PAGE_SIZE>,
+        data: &[Option<V>],
+    ) -> io::Result<()> {
+        let mut bitmap = vec![0u8; data.len().div_ceil(8)];
+
+        let values = data
+            .iter()
+            .enumerate()
+            .map(|(i, item)| match item {
+                Some(value) => {
+                    bitmap[i / 8] |= 1 << (i % 8);
+
+                    *value
+                }
+                None => V::zeroed(),
+            })
+            .collect::<Vec<_>>();
+
+        file.write_all(&(data.len() as u64).to_le_bytes())?;
+        file.write_all(&bitmap)?;
+        Plain::write(file, page, &values)
+    }
+}
+
+impl<V> DenseFormat<Option<V>> for Nullable where V: NoUninit + AnyBitPattern {}
+
+impl<V> StoredAs<Nullable> for Option<V> where V: NoUninit + AnyBitPattern {}
+
+/// Sparse items are stored as the present entries only: `count` as little-endian `u64`,
+/// the sorted index column and the [`Plain`] value column.
+pub struct Sparse;
+
+impl<V> PageFormat<(Idx, V)> for Sparse
+where
+    V: NoUninit + AnyBitPattern,
+{
+    const KIND: Option<&'static str> = Some("sparse");
+
+    fn read<const PAGE_SIZE: Idx>(
+        file: &mut File,
+        page: &PageRange<PAGE_SIZE>,
+    ) -> io::Result<Vec<(Idx, V)>> {
+        file.rewind()?;
+        let count = read_u64s(file, 1)?[0] as usize;
+
+        let mut indexes = vec![0 as Idx; count];
+        file.read_exact(cast_slice_mut(&mut indexes))?;
+
+        let base = page.page * PAGE_SIZE;
+        let from = indexes.partition_point(|idx| *idx < base + page.first);
+        let to = indexes.partition_point(|idx| *idx <= base + page.last);
+
+        let header = size_of::<u64>() + count * size_of::<Idx>();
+
+        let mut values = vec![V::zeroed(); to - from];
+        file.seek(SeekFrom::Start((header + from * size_of::<V>()) as u64))?;
+        file.read_exact(cast_slice_mut(&mut values))?;
+
+        Ok(indexes[from..to].iter().copied().zip(values).collect())
+    }
+
+    fn write<const PAGE_SIZE: Idx>(
+        file: &mut impl Write,
+        page: &PageRange<PAGE_SIZE>,
+        data: &[(Idx, V)],
+    ) -> io::Result<()> {
+        let (indexes, values): (Vec<_>, Vec<_>) = data.iter().copied().unzip();
+
+        file.write_all(&(data.len() as u64).to_le_bytes())?;
+        file.write_all(cast_slice(&indexes))?;
+        Plain::write(file, page, &values)
+    }
+
+    fn fits<const PAGE_SIZE: Idx>(
+        page: &PageRange<PAGE_SIZE>,
+        (idx, _): &(Idx, V),
+        _taken: usize,
+    ) -> bool {
+        let base = page.page * PAGE_SIZE;
+
+        (base + page.first..=base + page.last).contains(idx)
+    }
+
+    fn expected<const PAGE_SIZE: Idx>(_page: &PageRange<PAGE_SIZE>, taken: usize) -> usize {
+        taken
+    }
+
+    fn index((idx, _): &(Idx, V)) -> Option<Idx> {
+        Some(*idx)
+    }
+}
+
+impl<V> StoredAs<Sparse> for (Idx, V) where V: NoUninit + AnyBitPattern {}
+
+fn read_u64s(file: &mut File, n: usize) -> io::Result<Vec<u64>> {
+    let mut buf = vec![0u8; n * size_of::<u64>()];
+    file.read_exact(&mut buf)?;
+
+    Ok(buf
+        .chunks_exact(size_of::<u64>())
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .collect())
+}
@@ -0,0 +1,156 @@
+use std::hash::Hash;
+use std::sync::Arc;
+
+use futures::stream::{self, BoxStream, StreamExt};
+use futures::Future;
+
+use tokio::fs::create_dir_all;
+
+use bytemuck::{AnyBitPattern, NoUninit};
+
+use crate::Idx;
+use crate::{Config, TypedConfig};
+
+use crate::pages::PagesRange;
+use crate::source::{PageSource, SourceRange};
+
+use super::{
+    infallible, load_or_fetch_page, pages_dir, resolve_range, PagesDir, PagesStream, Plain,
+    SourcePolicy, StoreError,
+};
+
+pub trait PageStore<'a, R, K, V>: PageSource<K, V>
+where
+    R: SourceRange + Send + 'a,
+    K: Send + Sync + Copy + Hash + 'a,
+    V: NoUninit + AnyBitPattern + Send + Sync,
+{
+    fn load<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE>,
+    ) -> impl Future<Output = BoxStream<'a, V>> + Send {
+        async move {
+            match resolve_range(r, self.end(&k), config) {
+                Ok(Some(range)) => {
+                    let dir = pages_dir::<Plain, _, _, PAGE_SIZE>(k, config);
+
+                    if let Err(err) = create_dir_all(dir.as_ref()).await {
+                        panic!(
+                            "{}",
+                            StoreError::<()>::PathAccess(err, dir.as_ref().clone())
+                        );
+                    }
+
+                    infallible(self.load_or_cache::<PAGE_SIZE>(
+                        dir,
+                        k,
+                        (&range).into(),
+                        config.config.clone(),
+                    ))
+                    .flat_map(|chunk| stream::iter(chunk.values))
+                    .boxed()
+                }
+                Ok(None) => stream::empty().boxed(),
+                Err(_) => panic!("{}", StoreError::<()>::Unbounded),
+            }
+        }
+    }
+
+    /// Loads the cached pages and fetches the missing ones, up to `config.parallelism` at once,
+    /// yielding them in order.
+    fn load_or_cache<const PAGE_SIZE: Idx>(
+        &'a self,
+        dir: PagesDir,
+        k: K,
+        pages: PagesRange<PAGE_SIZE>,
+        config: Config<PAGE_SIZE>,
+    ) -> PagesStream<'a, V> {
+        let end_of_data = config.end.is_some();
+        let policy = SourcePolicy::new(&config);
+        let writer = config.write_behind.clone();
+        let summary = config.summary.clone();
+
+        stream::iter(pages.pages())
+            .map(move |page| {
+                load_or_fetch_page::<_, (), _, _, PAGE_SIZE>(
+                    Arc::clone(&dir),
+                    page,
+                    move |page| async move { self.page_source(k, page).await.map(Ok) },
+                    end_of_data,
+                    policy.clone(),
+                    writer.clone(),
+                    summary.clone(),
+                )
+            })
+            .buffered(config.parallelism.max(1))
+            .boxed()
+    }
+
+    fn page_source(&'a self, k: K, page: Idx) -> Self::Fut {
+        self(k, page)
+    }
+
+    fn config<const PAGE_SIZE: Idx>(&self) -> TypedConfig<V, PAGE_SIZE> {
+        TypedConfig::new()
+    }
+}
+
+impl<'a, F, R, K, V> PageStore<'a, R, K, V> for F
+where
+    F: PageSource<K, V>,
+    R: SourceRange + Send + 'a,
+    K: Send + Sync + Copy + Hash + 'a,
+    V: NoUninit + AnyBitPattern + Send + Sync,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::fs::remove_dir_all;
+
+    use super::*;
+
+    const PAGE_SIZE: Idx = 1024;
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    async fn source(_k: &(), page: Idx) -> BoxStream<'static, Idx> {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+
+        stream::iter(page * PAGE_SIZE..(page + 1) * PAGE_SIZE).boxed()
+    }
+
+    #[tokio::test]
+    async fn test_load() {
+        let mut config = TypedConfig::<Idx, PAGE_SIZE>::new();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.parallelism = 3;
+
+        let range = 512..4096 as Idx;
+
+        let values_1 = source
+            .load::<PAGE_SIZE>(&(), range.clone(), &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values_1, range.clone().collect::<Vec<_>>());
+        assert_eq!(CALLS.load(Ordering::SeqCst), 4);
+
+        let values_2 = source
+            .load::<PAGE_SIZE>(&(), 0..5000 as Idx, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values_2, (0..5000).collect::<Vec<_>>());
+        assert_eq!(CALLS.load(Ordering::SeqCst), 5);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+}
@@ -1,75 +1,362 @@
 use std::hash::Hash;
-use std::sync::Arc;
+use std::num::NonZero;
 
-use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
+use futures::{Future, FutureExt};
 
-use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
-
-use tokio::fs::{create_dir_all, try_exists};
-
-use bytemuck::{AnyBitPattern, NoUninit};
-
-use crate::TypedConfig;
+use crate::governor::govern;
+use crate::{CancelToken, Config, Pyramid, Summary, TypedConfig};
 use crate::{Idx, IdxRange};
 
 use crate::pages::PagesRange;
 use crate::source::{Source, SourceRange};
 
-use super::{cache_pages, load_pages, pages_dir, store_pages_range, PagesDir, StoreError};
+use super::{
+    aggregate_range, get_item, get_items, infallible, level_end, load_level_range,
+    load_or_cache_pages, load_range, load_range_rev, load_range_strided, load_ranges, pages_dir,
+    resolve_range, untimed, Attached, Chunk, DenseFormat, Detached, PageFormat, PagesDir,
+    PagesStream, Plain, SourceBounds, StoreError, StoredAs, TaggedChunk,
+};
 
-#[async_trait]
-pub trait Store<'a, R, K, V>: Source<R, K, V>
+/// Caches the items of a [`Source`] in pages of the format `F`, which the item type picks through [`StoredAs`].
+pub trait Store<'a, R, K, V, F = Plain>: Source<R, K, V>
 where
     R: SourceRange + Send + 'a,
     K: Send + Sync + Copy + Hash + 'a,
-    V: NoUninit + AnyBitPattern + Send + Sync,
+    V: StoredAs<F> + Send + Sync + 'static,
+    F: PageFormat<V>,
 {
-    async fn load<const PAGE_SIZE: Idx>(
+    /// Loads the items of `r`, reading the cached pages and filling the others from the source.
+    /// Reading cached pages boxes only the returned stream, the rest being the read of each page.
+    /// Having no error to yield, the loads end at the first source call past the timeouts of `config`:
+    /// the stream is then cut after the last whole page before that call, with no sign of the cut.
+    /// For the loads that must be told complete from cut, make the source fallible and load it
+    /// with [`TryStore::try_load_cancellable`](crate::TryStore::try_load_cancellable), which ends with the error instead.
+    fn load<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, V>> + Send {
+        async move {
+            match resolve_range(r, self.end(&k), config) {
+                Ok(Some(range)) => infallible(
+                    load_range::<F, _, (), _, _, PAGE_SIZE>(
+                        pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                        range,
+                        move |pages| self.pages_source(k, pages).map(|source| source.map(Ok)),
+                        SourceBounds::new::<R, PAGE_SIZE>(config),
+                        config,
+                        Attached,
+                    )
+                    .await,
+                )
+                .map(|chunk| stream::iter(chunk.values))
+                .flatten()
+                .boxed(),
+                Ok(None) => stream::empty().boxed(),
+                Err(r) => govern(config.governor.as_deref(), self(k, r)).await.boxed(),
+            }
+        }
+    }
+
+    /// Like [`Store::load`], but ends once `cancel` is cancelled, the source call in progress being dropped.
+    /// As with a timeout, the stream is cut after the last whole page, with no sign of the cut.
+    fn load_cancellable<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+        cancel: &CancelToken,
+    ) -> impl Future<Output = BoxStream<'a, V>> + Send {
+        let config = config.cancelled_by(cancel);
+
+        async move { self.load::<PAGE_SIZE>(k, r, &config).await }
+    }
+
+    /// Like [`Store::load`], but yields whole pages at once, each as a [`Chunk`] of values with their `IdxRange`.
+    /// Panics on an open range when the end of the data is not configured.
+    fn load_chunks<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, Chunk<V>>> + Send {
+        async move {
+            match resolve_range(r, self.end(&k), config) {
+                Ok(Some(range)) => infallible(
+                    load_range::<F, _, (), _, _, PAGE_SIZE>(
+                        pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                        range,
+                        move |pages| self.pages_source(k, pages).map(|source| source.map(Ok)),
+                        SourceBounds::new::<R, PAGE_SIZE>(config),
+                        config,
+                        Attached,
+                    )
+                    .await,
+                )
+                .boxed(),
+                Ok(None) => stream::empty().boxed(),
+                Err(_) => panic!("{}", StoreError::<()>::Unbounded),
+            }
+        }
+    }
+
+    /// Like [`Store::load`], but yields the items in descending index order, from the end of `r`,
+    /// so that the newest ones are read first. Panics on an open range when the end of the data is not configured.
+    fn load_rev<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, V>> + Send {
+        async move {
+            match resolve_range(r, self.end(&k), config) {
+                Ok(Some(range)) => infallible(
+                    load_range_rev::<F, _, (), _, _, PAGE_SIZE>(
+                        pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                        range,
+                        move |pages| self.pages_source(k, pages).map(|source| source.map(Ok)),
+                        SourceBounds::new::<R, PAGE_SIZE>(config),
+                        config,
+                    )
+                    .await,
+                )
+                .map(|chunk| stream::iter(chunk.values.into_iter().rev()))
+                .flatten()
+                .boxed(),
+                Ok(None) => stream::empty().boxed(),
+                Err(_) => panic!("{}", StoreError::<()>::Unbounded),
+            }
+        }
+    }
+
+    /// Loads every `step`-th item of `r`, from its start, reading only their slots from the cached pages.
+    /// The uncached pages are filled whole, so they are cached for the next loads.
+    /// Panics on an open range when the end of the data is not configured.
+    fn load_strided<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        step: NonZero<Idx>,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, V>> + Send
+    where
+        F: DenseFormat<V>,
+    {
+        async move {
+            match resolve_range(r, self.end(&k), config) {
+                Ok(Some(range)) => infallible(
+                    load_range_strided::<F, _, (), _, _, PAGE_SIZE>(
+                        pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                        range,
+                        step,
+                        move |pages| self.pages_source(k, pages).map(|source| source.map(Ok)),
+                        SourceBounds::new::<R, PAGE_SIZE>(config),
+                        config,
+                    )
+                    .await,
+                )
+                .boxed(),
+                Ok(None) => stream::empty().boxed(),
+                Err(_) => panic!("{}", StoreError::<()>::Unbounded),
+            }
+        }
+    }
+
+    /// Loads several ranges in one go, yielding the chunks of each tagged with the range they belong to.
+    /// The overlapping and adjacent ranges are merged first, and the tags are the merged ranges.
+    fn load_ranges<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        ranges: impl IntoIterator<Item = IdxRange>,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, TaggedChunk<V>>> + Send {
+        let ranges = ranges
+            .into_iter()
+            .filter_map(|range| match config.end {
+                Some(end) => range.truncate(end),
+                None => Some(range),
+            })
+            .collect();
+
+        async move {
+            infallible(
+                load_ranges::<F, _, (), _, _, PAGE_SIZE>(
+                    pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                    ranges,
+                    move |pages| self.pages_source(k, pages).map(|source| source.map(Ok)),
+                    SourceBounds::new::<R, PAGE_SIZE>(config),
+                    config,
+                )
+                .await,
+            )
+            .boxed()
+        }
+    }
+
+    /// Like [`Store::load`], but the source call in progress when the stream is dropped
+    /// is finished in a background task, so the pages it was asked for still get cached.
+    fn load_detached<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, V>> + Send
+    where
+        Self::Stream: 'static,
+    {
+        async move {
+            match resolve_range(r, self.end(&k), config) {
+                Ok(Some(range)) => infallible(
+                    load_range::<F, _, (), _, _, PAGE_SIZE>(
+                        pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                        range,
+                        move |pages| self.pages_source(k, pages).map(|source| source.map(Ok)),
+                        SourceBounds::new::<R, PAGE_SIZE>(config),
+                        config,
+                        Detached,
+                    )
+                    .await,
+                )
+                .map(|chunk| stream::iter(chunk.values))
+                .flatten()
+                .boxed(),
+                Ok(None) => stream::empty().boxed(),
+                Err(r) => govern(config.governor.as_deref(), self(k, r)).await.boxed(),
+            }
+        }
+    }
+
+    /// Loads the item at `idx`, reading only its slot when its page is cached,
+    /// and asking the source for it alone otherwise.
+    fn get<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        idx: Idx,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = Option<V>> + Send
+    where
+        V: Clone,
+        F: DenseFormat<V>,
+    {
+        async move {
+            get_item::<F, V, (), _, _, PAGE_SIZE>(
+                pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                idx,
+                move |range| {
+                    self.idx_range_source::<PAGE_SIZE>(k, range)
+                        .map(|source| source.map(Ok))
+                },
+                SourceBounds::new::<R, PAGE_SIZE>(config),
+                &untimed(config),
+            )
+            .await
+            .unwrap_or_else(|err| panic!("{}", err))
+        }
+    }
+
+    /// Loads the items at `idxs`, in order, with one read or source call per page they fall in,
+    /// `None` for those past the end of the data. Panics when the source yields fewer items than asked.
+    /// Having no error to yield, it waits for the source past the timeouts of `config`.
+    fn get_many<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        idxs: &[Idx],
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = Vec<Option<V>>> + Send
+    where
+        V: Clone,
+        F: DenseFormat<V>,
+    {
+        async move {
+            get_items::<F, V, (), _, _, PAGE_SIZE>(
+                pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                idxs,
+                move |range| {
+                    self.idx_range_source::<PAGE_SIZE>(k, range)
+                        .map(|source| source.map(Ok))
+                },
+                SourceBounds::new::<R, PAGE_SIZE>(config),
+                &untimed(config),
+            )
+            .await
+            .unwrap_or_else(|err| panic!("{}", err))
+        }
+    }
+
+    /// Summarizes the items of `r` as `S`, reading the stored summaries of the full cached pages
+    /// and loading only the pages at the edges of `r`, or those not summarized yet.
+    /// The full pages are summarized as they are cached when `config` [summarizes](TypedConfig::summarize) as `S`.
+    /// Having no error to yield, it waits for the source past the timeouts of `config`.
+    /// Panics on an open range when the end of the data is not configured.
+    fn aggregate<S: Summary<V>, const PAGE_SIZE: Idx>(
         &'a self,
         k: K,
         r: R,
-        config: &TypedConfig<V, PAGE_SIZE>,
-    ) -> BoxStream<'a, V>
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = Option<S>> + Send {
+        async move {
+            match resolve_range(r, self.end(&k), config) {
+                Ok(Some(range)) => aggregate_range::<F, _, _, (), _, _, PAGE_SIZE>(
+                    pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                    range,
+                    move |pages| self.pages_source(k, pages).map(|source| source.map(Ok)),
+                    SourceBounds::new::<R, PAGE_SIZE>(config),
+                    &untimed(config),
+                )
+                .await
+                .unwrap_or_else(|err| panic!("{}", err)),
+                Ok(None) => None,
+                Err(_) => panic!("{}", StoreError::<()>::Unbounded),
+            }
+        }
+    }
+
+    /// Loads `range` at `level` of the pyramid over the items, its indices being those of the level:
+    /// level 0 holds the items, and each value of level `l + 1` reduces `PAGE_SIZE` values of level `l`,
+    /// as `config` [reduces them](TypedConfig::reduce_levels), so `PAGE_SIZE^l` items.
+    /// The level pages are built from the cached pages on the first load and stored,
+    /// and built again once one of these pages is cached anew. Panics when `config` has no reduction.
+    fn load_level<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        range: IdxRange,
+        level: u32,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, V>> + Send
     where
-        'a: 'async_trait,
+        F: DenseFormat<V>,
     {
-        match r.try_into() {
-            Ok(range) => {
-                let dir = pages_dir(k, config);
-
-                let sealed = match try_exists(dir.as_ref()).await {
-                    Ok(exists) => {
-                        if exists {
-                            self.load_or_cache::<PAGE_SIZE>(dir, k, (&range).into())
-                                .boxed()
-                        } else {
-                            create_dir_all(dir.as_ref()).await.unwrap();
-
-                            let pages = (&range).into();
-                            let source = self.idx_range_source::<PAGE_SIZE>(k, range).await;
-
-                            cache_pages::<_, PAGE_SIZE>(dir, pages, source)
-                                .await
-                                .boxed()
-                        }
-                    }
-                    Err(err) => {
-                        panic!(
-                            "{}",
-                            StoreError::<()>::PathAccess(err, dir.as_ref().clone())
-                        );
-                    }
-                };
-
-                sealed
-                    .map(|result| match result {
-                        Ok(item) => stream::iter(item),
-                        Err(err) => panic!("{}", err),
-                    })
-                    .flatten()
-                    .boxed()
+        let reduce = config
+            .pyramid
+            .as_ref()
+            .and_then(Pyramid::reduce)
+            .expect("a level load needs the levels reduction in the config");
+        let range = match level_end::<PAGE_SIZE>(config.end, level) {
+            Some(end) => range.truncate(end),
+            None => Some(range),
+        };
+
+        async move {
+            match range {
+                Some(range) => infallible(
+                    load_level_range::<F, _, (), _, _, PAGE_SIZE>(
+                        pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                        range,
+                        level,
+                        move |pages| self.pages_source(k, pages).map(|source| source.map(Ok)),
+                        SourceBounds::new::<R, PAGE_SIZE>(config),
+                        config,
+                        reduce,
+                    )
+                    .await,
+                )
+                .map(|chunk| stream::iter(chunk.values))
+                .flatten()
+                .boxed(),
+                None => stream::empty().boxed(),
             }
-            Err(r) => self(k, r).await.boxed(),
         }
     }
 
@@ -78,25 +365,16 @@ where
         dir: PagesDir,
         k: K,
         pages: PagesRange<PAGE_SIZE>,
-    ) -> BoxStream<'a, Result<Vec<V>, StoreError>> {
-        async_stream::try_stream! {
-            for await result in store_pages_range::<(), PAGE_SIZE>(dir.as_ref(), pages).await {
-                let store_pages = result?;
-
-                let pages_data = if store_pages.cached {
-                    load_pages::<V, (), PAGE_SIZE>(Arc::clone(&dir), store_pages.pages).boxed()
-                } else {
-                    let pages = store_pages.pages.clone();
-                    let source = self.pages_source(k, store_pages.pages).await;
-
-                    cache_pages::<_, PAGE_SIZE>(Arc::clone(&dir), pages, source)
-                        .await.boxed()
-                };
-
-                yield pages_data;
-            }
-        }
-        .try_flatten()
+        config: Config<PAGE_SIZE>,
+    ) -> PagesStream<'a, V> {
+        load_or_cache_pages::<F, _, _, _, _, PAGE_SIZE>(
+            dir,
+            pages,
+            move |pages| self.pages_source(k, pages).map(|source| source.map(Ok)),
+            SourceBounds::new::<R, PAGE_SIZE>(&config),
+            &config,
+            Attached,
+        )
         .boxed()
     }
 
@@ -116,18 +394,18 @@ where
         self.idx_range_source::<PAGE_SIZE>(k, idx_range)
     }
 
-    fn config<const PAGE_SIZE: Idx>(&self) -> TypedConfig<V, PAGE_SIZE> {
+    fn config<const PAGE_SIZE: Idx>(&self) -> TypedConfig<V, PAGE_SIZE, F> {
         TypedConfig::new()
     }
 }
 
-#[async_trait]
-impl<'a, F, R, K, V> Store<'a, R, K, V> for F
+impl<'a, S, R, K, V, F> Store<'a, R, K, V, F> for S
 where
-    F: Source<R, K, V>,
+    S: Source<R, K, V>,
     R: SourceRange + Send + 'a,
     K: Send + Sync + Copy + Hash + 'a,
-    V: NoUninit + AnyBitPattern + Send + Sync,
+    V: StoredAs<F> + Send + Sync + 'static,
+    F: PageFormat<V>,
 {
 }
 
@@ -136,7 +414,7 @@ mod tests {
     use std::ops::Range;
     use std::path::PathBuf;
 
-    use tokio::fs::remove_dir_all;
+    use tokio::fs::{remove_dir_all, remove_file, try_exists};
 
     use super::*;
 
@@ -169,4 +447,1211 @@ mod tests {
 
         remove_dir_all(&config.root).await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_load_bounded() {
+        use std::ops::RangeFrom;
+
+        const END: Idx = 3000;
+
+        async fn source(_k: &(), range: RangeFrom<Idx>) -> BoxStream<'static, Idx> {
+            stream::iter(range.start..END).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        let values = source
+            .load::<PAGE_SIZE>(&(), 512.., &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, (512..END).collect::<Vec<_>>());
+
+        config.end = Some(END);
+
+        for _ in 0..2 {
+            let values = source
+                .load::<PAGE_SIZE>(&(), 512.., &config)
+                .await
+                .collect::<Vec<_>>()
+                .await;
+
+            assert_eq!(values, (512..END).collect::<Vec<_>>());
+        }
+
+        config.end = Some(END + 500);
+
+        let values = source
+            .load::<PAGE_SIZE>(&(), 1000.., &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, (1000..END).collect::<Vec<_>>());
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_full() {
+        use std::ops::RangeFull;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        const END: Idx = 3000;
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        async fn source(_k: &(), _r: RangeFull) -> BoxStream<'static, Idx> {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+
+            stream::iter(0..END).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        let values = source
+            .load::<PAGE_SIZE>(&(), .., &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, (0..END).collect::<Vec<_>>());
+
+        config.end = Some(END);
+
+        source
+            .load::<PAGE_SIZE>(&(), .., &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        // the source starts from the first item, so the ones before an uncached page are dropped
+        let dir = pages_dir::<Plain, _, _, PAGE_SIZE>(&(), &config);
+        remove_file(dir.as_ref().join("1")).await.unwrap();
+
+        let values = source
+            .load::<PAGE_SIZE>(&(), .., &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, (0..END).collect::<Vec<_>>());
+
+        // the partial last page is never cached, so its items are fetched from the first one too
+        let items = source
+            .get_many::<PAGE_SIZE>(&(), &[100, 2500], &config)
+            .await;
+
+        assert_eq!(items, [Some(100), Some(2500)]);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 4);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_plain_layout() {
+        use std::any::TypeId;
+        use std::hash::{BuildHasher, Hasher};
+        use std::mem::size_of;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use ahash::RandomState;
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        // the directory as it was hashed before the page formats, where the `()` key hashes nothing
+        let mut hasher = RandomState::with_seeds(
+            2858199611238995053,
+            11086922458483105823,
+            15118288254885688199,
+            573726014207964826,
+        )
+        .build_hasher();
+        TypeId::of::<TypedConfig<Idx, PAGE_SIZE>>().hash(&mut hasher);
+        size_of::<Idx>().hash(&mut hasher);
+        let dir = config.root.join(format!("{}", hasher.finish()));
+
+        assert_eq!(*pages_dir::<Plain, _, _, PAGE_SIZE>(&(), &config), dir);
+
+        let values = source
+            .load::<PAGE_SIZE>(&(), 0..2048, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, (0..2048).collect::<Vec<_>>());
+        assert_eq!(try_exists(dir.join("1")).await.unwrap(), true);
+
+        let values = source
+            .load::<PAGE_SIZE>(&(), 0..2048, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, (0..2048).collect::<Vec<_>>());
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_empty() {
+        use bytemuck::{Pod, Zeroable};
+
+        #[derive(Clone, Copy, Pod, Zeroable)]
+        #[repr(C)]
+        struct Sum(u64);
+
+        impl Summary<Idx> for Sum {
+            fn of(item: &Idx) -> Self {
+                Sum(*item as u64)
+            }
+
+            fn combine(self, next: Self) -> Self {
+                Sum(self.0 + next.0)
+            }
+        }
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        assert_eq!(
+            source
+                .load_chunks::<PAGE_SIZE>(&(), 5..5, &config)
+                .await
+                .count()
+                .await,
+            0
+        );
+        assert_eq!(
+            source
+                .load_rev::<PAGE_SIZE>(&(), 5..5, &config)
+                .await
+                .count()
+                .await,
+            0
+        );
+        assert_eq!(
+            source
+                .load_strided::<PAGE_SIZE>(&(), 5..5, NonZero::new(2).unwrap(), &config)
+                .await
+                .count()
+                .await,
+            0
+        );
+        assert_eq!(
+            source
+                .aggregate::<Sum, PAGE_SIZE>(&(), 5..5, &config)
+                .await
+                .map(|Sum(sum)| sum),
+            None
+        );
+
+        config.end = Some(3000);
+
+        assert_eq!(
+            source
+                .load_rev::<PAGE_SIZE>(&(), 4000..5000, &config)
+                .await
+                .count()
+                .await,
+            0
+        );
+        assert!(!try_exists(&config.root).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_load_prefetch() {
+        use std::sync::LazyLock;
+        use std::time::Duration;
+
+        use tokio::sync::Barrier;
+        use tokio::time::timeout;
+
+        // the calls for both uncached runs must be in flight at once
+        static BARRIER: LazyLock<Barrier> = LazyLock::new(|| Barrier::new(2));
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            if range.start != 1024 {
+                BARRIER.wait().await;
+            }
+
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.prefetch = 1;
+
+        source
+            .load::<PAGE_SIZE>(&(), 1024..2048, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let values = timeout(
+            Duration::from_secs(10),
+            source
+                .load::<PAGE_SIZE>(&(), 0..3000, &config)
+                .await
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(values, (0..3000).collect::<Vec<_>>());
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_prefetch_calls() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        use tokio::time::sleep;
+
+        static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+        static MAX_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            let in_flight = IN_FLIGHT.fetch_add(1, Ordering::SeqCst) + 1;
+            MAX_IN_FLIGHT.fetch_max(in_flight, Ordering::SeqCst);
+
+            sleep(Duration::from_millis(50)).await;
+            IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.prefetch = 1;
+
+        // the uncached runs of the ranges follow each other, with no cached run between them
+        let ranges = [0..100, 5000..5100, 10000..10100, 15000..15100 as Idx]
+            .map(|range| IdxRange::try_from(range).unwrap());
+
+        let tagged = source
+            .load_ranges::<PAGE_SIZE>(&(), ranges, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(tagged.len(), 4);
+        // the current call, and the one prefetched
+        assert_eq!(MAX_IN_FLIGHT.load(Ordering::SeqCst), 2);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_max_source_range() {
+        use std::sync::Mutex;
+
+        static CALLS: Mutex<Vec<Range<Idx>>> = Mutex::new(Vec::new());
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            CALLS.lock().unwrap().push(range.clone());
+
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.max_source_range = Some(2500);
+
+        let values = source
+            .load::<PAGE_SIZE>(&(), 100..200, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, (100..200).collect::<Vec<_>>());
+
+        let mut values = source.load::<PAGE_SIZE>(&(), 1000..7000, &config).await;
+
+        assert_eq!(values.next().await, Some(1000));
+        assert_eq!(*CALLS.lock().unwrap(), [100..200, 1000..2048]);
+
+        drop(values);
+
+        let values = source
+            .load::<PAGE_SIZE>(&(), 0..7000, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, (0..7000).collect::<Vec<_>>());
+        assert_eq!(
+            *CALLS.lock().unwrap(),
+            [
+                100..200,
+                1000..2048,
+                0..2048,
+                2048..4096,
+                4096..6144,
+                6144..7000
+            ]
+        );
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_governed() {
+        use std::sync::Arc;
+        use std::time::{Duration, Instant};
+
+        use crate::Governor;
+
+        async fn source(_k: &u8, range: Range<Idx>) -> BoxStream<'static, Idx> {
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.governor = Some(Arc::new(
+            Governor::new().max_calls(1, Duration::from_millis(200)),
+        ));
+
+        let start = Instant::now();
+
+        for k in [&1, &2] {
+            source
+                .load::<PAGE_SIZE>(k, 0..100, &config)
+                .await
+                .collect::<Vec<_>>()
+                .await;
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_governed_prefetch() {
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        use tokio::time::timeout;
+
+        use crate::Governor;
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.governor = Some(Arc::new(Governor::new().max_concurrent(1)));
+        config.prefetch = 1;
+        config.max_source_range = Some(2 * PAGE_SIZE);
+
+        source
+            .load::<PAGE_SIZE>(&(), 4 * PAGE_SIZE..5 * PAGE_SIZE, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        // the run past the cached page is prefetched while the first one is read in two calls
+        let values = timeout(
+            Duration::from_secs(5),
+            source
+                .load::<PAGE_SIZE>(&(), 0..8 * PAGE_SIZE, &config)
+                .await
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(values, (0..8 * PAGE_SIZE).collect::<Vec<_>>());
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_cancellable() {
+        use std::time::Duration;
+
+        use tokio::time::timeout;
+
+        use crate::CancelToken;
+
+        // the second page never comes
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            stream::iter(range.start..1500)
+                .chain(stream::pending())
+                .boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.item_timeout = Some(Duration::from_millis(50));
+
+        // the load is cut after the last whole page, the items of the timed-out one being dropped,
+        // and that page is not cached, so the same load is cut again
+        for _ in 0..2 {
+            let values = source
+                .load::<PAGE_SIZE>(&(), 0..2048, &config)
+                .await
+                .collect::<Vec<_>>()
+                .await;
+
+            assert_eq!(values, (0..1024).collect::<Vec<_>>());
+        }
+
+        config.item_timeout = None;
+
+        let cancel = CancelToken::new();
+        tokio::spawn({
+            let cancel = cancel.clone();
+
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                cancel.cancel();
+            }
+        });
+
+        let values = timeout(
+            Duration::from_secs(10),
+            source
+                .load_cancellable::<PAGE_SIZE>(&(), 1024..2048, &config, &cancel)
+                .await
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+
+        assert!(values.is_empty());
+
+        // the token stops only the load it was given to
+        let values = timeout(
+            Duration::from_secs(10),
+            source
+                .load::<PAGE_SIZE>(&(), 0..1024, &config)
+                .await
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(values, (0..1024).collect::<Vec<_>>());
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_write_behind() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use crate::WriteBehind;
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let writer = Arc::new(WriteBehind::new(2));
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.write_behind = Some(Arc::clone(&writer));
+
+        let range = 0..8 * PAGE_SIZE;
+
+        for _ in 0..2 {
+            let values = source
+                .load::<PAGE_SIZE>(&(), range.clone(), &config)
+                .await
+                .collect::<Vec<_>>()
+                .await;
+
+            assert_eq!(values, range.clone().collect::<Vec<_>>());
+
+            writer.flush().await.unwrap();
+        }
+
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_detached() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        use crate::WriteBehind;
+
+        use super::super::page_path;
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        // the pages only show up once fully written
+        config.write_behind = Some(Arc::new(WriteBehind::default()));
+
+        let range = 0..8 * PAGE_SIZE;
+
+        let values = source
+            .load_detached::<PAGE_SIZE>(&(), range.clone(), &config)
+            .await
+            .take(10)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, (0..10).collect::<Vec<_>>());
+
+        let dir = pages_dir::<Plain, _, _, PAGE_SIZE>(&(), &config);
+
+        while !(0..8).all(|page| page_path(dir.as_ref(), &page).exists()) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let values = source
+            .load::<PAGE_SIZE>(&(), range.clone(), &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, range.collect::<Vec<_>>());
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_chunks() {
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        let loads = [
+            (100..3000, vec![(100, 924), (1024, 1024), (2048, 952)]),
+            (
+                0..4000 as Idx,
+                vec![(0, 1024), (1024, 1024), (2048, 1024), (3072, 928)],
+            ),
+        ];
+
+        for (range, expected) in loads {
+            let chunks = source
+                .load_chunks::<PAGE_SIZE>(&(), range.clone(), &config)
+                .await
+                .collect::<Vec<_>>()
+                .await;
+
+            let bounds = chunks
+                .iter()
+                .map(|chunk| (chunk.range.start(), chunk.range.len()))
+                .collect::<Vec<_>>();
+
+            assert_eq!(bounds, expected);
+            assert_eq!(
+                chunks
+                    .into_iter()
+                    .flat_map(|chunk| chunk.values)
+                    .collect::<Vec<_>>(),
+                range.collect::<Vec<_>>()
+            );
+        }
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get() {
+        use std::sync::Mutex;
+
+        static CALLS: Mutex<Vec<Range<Idx>>> = Mutex::new(Vec::new());
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            CALLS.lock().unwrap().push(range.clone());
+
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        assert_eq!(source.get::<PAGE_SIZE>(&(), 42, &config).await, Some(42));
+        // the item of an uncached page is asked for alone
+        assert_eq!(CALLS.lock().unwrap()[..], [Range { start: 42, end: 43 }]);
+
+        source
+            .load::<PAGE_SIZE>(&(), 0..2 * PAGE_SIZE, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(CALLS.lock().unwrap().len(), 2);
+
+        let idxs = [2047, 5000, 3, 5010, 1024];
+        let items = source.get_many::<PAGE_SIZE>(&(), &idxs, &config).await;
+
+        assert_eq!(items, idxs.map(Some));
+        // the cached pages are read, the items of the uncached one asked in a single call
+        assert_eq!(CALLS.lock().unwrap().len(), 3);
+
+        config.end = Some(4000);
+
+        assert_eq!(source.get::<PAGE_SIZE>(&(), 4000, &config).await, None);
+        assert_eq!(CALLS.lock().unwrap().len(), 3);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_ranges() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        let ranges = [1900..2000, 100..200, 150..300, 300..310, 5000..5010 as Idx]
+            .map(|range| IdxRange::try_from(range).unwrap());
+
+        let tagged = source
+            .load_ranges::<PAGE_SIZE>(&(), ranges, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let expected = [100..310, 1900..2000, 5000..5010 as Idx].map(|range| {
+            let tag = IdxRange::try_from(range.clone()).unwrap();
+
+            (tag.clone(), Chunk::new(tag, range.collect()))
+        });
+
+        assert_eq!(tagged, expected);
+        // the first two ranges touch the same pages, so a single call fills both
+        assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_rev() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.max_source_range = Some(2 * PAGE_SIZE);
+
+        source
+            .load::<PAGE_SIZE>(&(), 2 * PAGE_SIZE..3 * PAGE_SIZE, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let range = 100..6 * PAGE_SIZE;
+
+        let newest = source
+            .load_rev::<PAGE_SIZE>(&(), range.clone(), &config)
+            .await
+            .take(10)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(newest, range.clone().rev().take(10).collect::<Vec<_>>());
+        // only the last two pages were asked for
+        assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+
+        let values = source
+            .load_rev::<PAGE_SIZE>(&(), range.clone(), &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, range.rev().collect::<Vec<_>>());
+        // the cached page splits the rest into two runs
+        assert_eq!(CALLS.load(Ordering::SeqCst), 4);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_rev_run() {
+        use std::sync::Mutex;
+
+        static CALLS: Mutex<Vec<Range<Idx>>> = Mutex::new(Vec::new());
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            CALLS.lock().unwrap().push(range.clone());
+
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        let newest = source
+            .load_rev::<PAGE_SIZE>(&(), 0..20 * PAGE_SIZE, &config)
+            .await
+            .take(10)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(
+            newest,
+            (0..20 * PAGE_SIZE).rev().take(10).collect::<Vec<_>>()
+        );
+        // without `max_source_range`, the run fetched ahead of the `take` is still bounded
+        let calls = CALLS.lock().unwrap().clone();
+
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0], 12 * PAGE_SIZE..20 * PAGE_SIZE);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_strided() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        source
+            .load::<PAGE_SIZE>(&(), PAGE_SIZE..2 * PAGE_SIZE, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let range = 0..4 * PAGE_SIZE;
+
+        for calls in [3, 3] {
+            let values = source
+                .load_strided::<PAGE_SIZE>(&(), range.clone(), NonZero::new(100).unwrap(), &config)
+                .await
+                .collect::<Vec<_>>()
+                .await;
+
+            assert_eq!(values, range.clone().step_by(100).collect::<Vec<_>>());
+            // the pages around the cached one are filled, and cached, by a call each
+            assert_eq!(CALLS.load(Ordering::SeqCst), calls);
+        }
+
+        // a step past the end of the range, even past `usize`, picks its first item alone
+        for step in [4 * PAGE_SIZE, usize::MAX as Idx + 1, Idx::MAX] {
+            let values = source
+                .load_strided::<PAGE_SIZE>(&(), 5..range.end, NonZero::new(step).unwrap(), &config)
+                .await
+                .collect::<Vec<_>>()
+                .await;
+
+            assert_eq!(values, [5]);
+        }
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_aggregate() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use bytemuck::{Pod, Zeroable};
+
+        use super::super::summary_path;
+
+        #[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+        #[repr(C)]
+        struct Stats {
+            sum: u64,
+            count: u64,
+        }
+
+        impl Summary<Idx> for Stats {
+            fn of(item: &Idx) -> Self {
+                Self {
+                    sum: *item as u64,
+                    count: 1,
+                }
+            }
+
+            fn combine(self, next: Self) -> Self {
+                Self {
+                    sum: self.sum + next.sum,
+                    count: self.count + next.count,
+                }
+            }
+        }
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+
+            stream::iter(range).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.summarize::<Stats>();
+
+        source
+            .load::<PAGE_SIZE>(&(), 0..4 * PAGE_SIZE, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let dir = pages_dir::<Plain, _, _, PAGE_SIZE>(&(), &config);
+        let summary = config.summary.as_ref().unwrap();
+
+        for page in 0..4 {
+            assert!(try_exists(summary_path(dir.as_ref(), &page, summary))
+                .await
+                .unwrap());
+        }
+
+        let range = 100..3000;
+        let expected = Stats {
+            sum: range.clone().sum::<Idx>() as u64,
+            count: (range.end - range.start) as u64,
+        };
+
+        for _ in 0..2 {
+            let stats = source
+                .aggregate::<Stats, PAGE_SIZE>(&(), range.clone(), &config)
+                .await;
+
+            assert_eq!(stats, Some(expected));
+            assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+        }
+
+        // the pages past the cached ones are filled, summarizing them on the way
+        let stats = source
+            .aggregate::<Stats, PAGE_SIZE>(&(), 3000..6 * PAGE_SIZE, &config)
+            .await
+            .unwrap();
+
+        assert_eq!(stats.count, 6 * PAGE_SIZE as u64 - 3000);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+        assert!(try_exists(summary_path(dir.as_ref(), &5, summary))
+            .await
+            .unwrap());
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_level() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use super::super::{level_dir, page_path};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        static OFFSET: AtomicUsize = AtomicUsize::new(0);
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Idx> {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+
+            let offset = OFFSET.load(Ordering::SeqCst) as Idx;
+
+            stream::iter(range).map(move |i| i + offset).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 16;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.end = Some(1024);
+        config.reduce_levels(|values| *values.iter().max().unwrap());
+
+        let level = |level: u32, range: Range<Idx>| {
+            let config = &config;
+
+            async move {
+                source
+                    .load_level::<PAGE_SIZE>(&(), range.try_into().unwrap(), level, config)
+                    .await
+                    .collect::<Vec<_>>()
+                    .await
+            }
+        };
+
+        for _ in 0..2 {
+            assert_eq!(
+                level(1, 0..100).await,
+                (0..64).map(|i| i * 16 + 15).collect::<Vec<_>>()
+            );
+            assert_eq!(level(2, 0..100).await, [255, 511, 767, 1023]);
+            assert_eq!(level(2, 1..3).await, [511, 767]);
+            assert_eq!(level(0, 1014..1024).await, (1014..1024).collect::<Vec<_>>());
+        }
+
+        // a call per level 1 page on the first build, the next loads reading the cache
+        assert_eq!(CALLS.load(Ordering::SeqCst), 4);
+
+        let dir = pages_dir::<Plain, _, _, PAGE_SIZE>(&(), &config);
+        let level_1 = level_dir(dir.as_ref(), 1);
+
+        // the level 2 page goes past the end of the data, so it is built on each load
+        assert!(try_exists(page_path(&level_1, &3)).await.unwrap());
+        assert!(!try_exists(page_path(level_dir(dir.as_ref(), 2), &0))
+            .await
+            .unwrap());
+
+        OFFSET.store(1000, Ordering::SeqCst);
+        remove_file(page_path(dir.as_ref(), &1)).await.unwrap();
+
+        source
+            .load::<PAGE_SIZE>(&(), 16..32, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        // caching the page again drops the level page over it, which is built again from it
+        assert!(!try_exists(page_path(&level_1, &0)).await.unwrap());
+        assert_eq!(level(1, 0..3).await, [15, 1031, 47]);
+        assert_eq!(level(2, 0..1).await, [1031]);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_codec() {
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, String> {
+            stream::iter(range)
+                .map(|i| "x".repeat(i as usize % 7))
+                .boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        let range = 512..4096 as Idx;
+
+        let values_1 = source
+            .load::<PAGE_SIZE>(&(), range.clone(), &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let values_2 = source
+            .load::<PAGE_SIZE>(&(), range, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values_1, values_2);
+        assert_eq!(
+            values_1,
+            source(&(), 512..4096).await.collect::<Vec<_>>().await
+        );
+
+        let items = source
+            .get_many::<PAGE_SIZE>(&(), &[1030, 3, 2000], &config)
+            .await;
+
+        assert_eq!(
+            items,
+            [Some("x".into()), Some("x".repeat(3)), Some("x".repeat(5))]
+        );
+
+        let values_rev = source
+            .load_rev::<PAGE_SIZE>(&(), 1000..1100, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(
+            values_rev,
+            (1000..1100)
+                .rev()
+                .map(|i| "x".repeat(i % 7))
+                .collect::<Vec<_>>()
+        );
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_nullable() {
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Option<Idx>> {
+            stream::iter(range)
+                .map(|i| (i % 3 != 0).then_some(i))
+                .boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        let range = 512..4096 as Idx;
+
+        let values_1 = source
+            .load::<PAGE_SIZE>(&(), range.clone(), &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let values_2 = source
+            .load::<PAGE_SIZE>(&(), range, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values_1, values_2);
+        assert_eq!(
+            values_1,
+            source(&(), 512..4096).await.collect::<Vec<_>>().await
+        );
+
+        let values_3 = source
+            .load::<PAGE_SIZE>(&(), 1501..2100, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(
+            values_3,
+            source(&(), 1501..2100).await.collect::<Vec<_>>().await
+        );
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_sparse() {
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, (Idx, u32)> {
+            stream::iter(range.filter(|i| i % 37 == 0).map(|i| (i, i as u32 * 2))).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        let range = 512..4096 as Idx;
+
+        let values_1 = source
+            .load::<PAGE_SIZE>(&(), range.clone(), &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let values_2 = source
+            .load::<PAGE_SIZE>(&(), range, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values_1, values_2);
+        assert_eq!(
+            values_1,
+            source(&(), 512..4096).await.collect::<Vec<_>>().await
+        );
+
+        let values_3 = source
+            .load::<PAGE_SIZE>(&(), 1500..2100, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(
+            values_3,
+            source(&(), 1500..2100).await.collect::<Vec<_>>().await
+        );
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
 }
@@ -10,7 +10,7 @@ use futures::Stream;
 use tokio::fs::try_exists;
 
 use crate::pages::PagesRange;
-use crate::{Idx, StoreError};
+use crate::{GapError, Idx, IdxRange, StoreError};
 
 pub struct StorePages<const PAGE_SIZE: Idx> {
     pub cached: bool,
@@ -26,9 +26,10 @@ impl<const PAGE_SIZE: Idx> MaybeAccumulable for StorePages<PAGE_SIZE> {
 pub async fn store_pages_range<E, const PAGE_SIZE: Idx>(
     dir: impl AsRef<Path>,
     pages: PagesRange<PAGE_SIZE>,
-) -> impl Stream<Item = Result<StorePages<PAGE_SIZE>, StoreError<E>>> {
+) -> impl Stream<Item = Result<StorePages<PAGE_SIZE>, GapError<E>>> {
     async_stream::stream! {
         for page in pages.pages() {
+            let range = IdxRange::from(&page);
             let file = super::page_path(&dir, &page.page);
 
             yield try_exists(&file)
@@ -37,7 +38,7 @@ pub async fn store_pages_range<E, const PAGE_SIZE: Idx>(
                     cached: exists,
                     pages: page.into(),
                 })
-                .map_err(|err| StoreError::PathAccess(err, file));
+                .map_err(|err| GapError::new(range, StoreError::PathAccess(err, file)));
         }
     }
     .try_partially_accumulate()
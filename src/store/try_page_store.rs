@@ -0,0 +1,161 @@
+use std::hash::Hash;
+use std::sync::Arc;
+
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use futures::Future;
+
+use tokio::fs::create_dir_all;
+
+use bytemuck::{AnyBitPattern, NoUninit};
+
+use crate::Idx;
+use crate::{Config, TypedConfig};
+
+use crate::pages::PagesRange;
+use crate::source::{SourceRange, TryPageSource};
+
+use super::{
+    load_or_fetch_page, pages_dir, resolve_range, PagesDir, PagesStream, Plain, SourcePolicy,
+    StoreError,
+};
+
+pub trait TryPageStore<'a, R, K, V>: TryPageSource<K, V>
+where
+    R: SourceRange + Send + 'a,
+    K: Send + Sync + Copy + Hash + 'a,
+    V: NoUninit + AnyBitPattern + Send + Sync,
+{
+    fn load<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE>,
+    ) -> impl Future<Output = BoxStream<'a, Result<V, Self::Error>>> + Send {
+        async move {
+            match resolve_range(r, self.end(&k), config) {
+                Ok(Some(range)) => {
+                    let dir = pages_dir::<Plain, _, _, PAGE_SIZE>(k, config);
+
+                    if let Err(err) = create_dir_all(dir.as_ref()).await {
+                        panic!(
+                            "{}",
+                            StoreError::<()>::PathAccess(err, dir.as_ref().clone())
+                        );
+                    }
+
+                    self.load_or_cache::<PAGE_SIZE>(dir, k, (&range).into(), config.config.clone())
+                        .map(|result| match result {
+                            Ok(chunk) => Ok(stream::iter(chunk.values.into_iter().map(Ok))),
+                            Err(gap) => match gap.error {
+                                StoreError::External(err) => Err(err),
+                                err => panic!("{}", err),
+                            },
+                        })
+                        .try_flatten()
+                        .boxed()
+                }
+                Ok(None) => stream::empty().boxed(),
+                Err(_) => panic!("{}", StoreError::<()>::Unbounded),
+            }
+        }
+    }
+
+    /// Loads the cached pages and fetches the missing ones, up to `config.parallelism` at once,
+    /// yielding them in order.
+    fn load_or_cache<const PAGE_SIZE: Idx>(
+        &'a self,
+        dir: PagesDir,
+        k: K,
+        pages: PagesRange<PAGE_SIZE>,
+        config: Config<PAGE_SIZE>,
+    ) -> PagesStream<'a, V, Self::Error> {
+        let end_of_data = config.end.is_some();
+        let policy = SourcePolicy::new(&config);
+        let writer = config.write_behind.clone();
+        let summary = config.summary.clone();
+
+        stream::iter(pages.pages())
+            .map(move |page| {
+                load_or_fetch_page::<_, _, _, _, PAGE_SIZE>(
+                    Arc::clone(&dir),
+                    page,
+                    move |page| self.page_source(k, page),
+                    end_of_data,
+                    policy.clone(),
+                    writer.clone(),
+                    summary.clone(),
+                )
+            })
+            .buffered(config.parallelism.max(1))
+            .boxed()
+    }
+
+    fn page_source(&'a self, k: K, page: Idx) -> Self::Fut {
+        self(k, page)
+    }
+
+    fn config<const PAGE_SIZE: Idx>(&self) -> TypedConfig<V, PAGE_SIZE> {
+        TypedConfig::new()
+    }
+}
+
+impl<'a, F, R, K, V> TryPageStore<'a, R, K, V> for F
+where
+    F: TryPageSource<K, V>,
+    R: SourceRange + Send + 'a,
+    K: Send + Sync + Copy + Hash + 'a,
+    V: NoUninit + AnyBitPattern + Send + Sync,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tokio::fs::remove_dir_all;
+
+    use super::*;
+
+    const PAGE_SIZE: Idx = 1024;
+    const END: Idx = 3000;
+
+    async fn source(_k: &(), page: Idx) -> BoxStream<'static, Result<Idx, Idx>> {
+        match page {
+            3 => stream::iter([Err(page)]).boxed(),
+            page => stream::iter(
+                (page * PAGE_SIZE..(page + 1) * PAGE_SIZE).take_while(|idx| *idx < END),
+            )
+            .map(Ok)
+            .boxed(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load() {
+        let mut config = TypedConfig::<Idx, PAGE_SIZE>::new();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.end = Some(END);
+
+        for _ in 0..2 {
+            let values = source
+                .load::<PAGE_SIZE>(&(), 512 as Idx.., &config)
+                .await
+                .try_collect::<Vec<_>>()
+                .await;
+
+            assert_eq!(values, Ok((512..END).collect::<Vec<_>>()));
+        }
+
+        config.end = Some(5000);
+
+        let values = source
+            .load::<PAGE_SIZE>(&(), 3072 as Idx.., &config)
+            .await
+            .try_collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, Err(3));
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+}
@@ -1,78 +1,403 @@
 use std::hash::Hash;
-use std::sync::Arc;
+use std::num::NonZero;
 
-use async_trait::async_trait;
+use futures::future::Either;
+use futures::stream::{self, BoxStream, Stream, StreamExt, TryStreamExt};
+use futures::Future;
 
-use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
-
-use tokio::fs::{create_dir_all, try_exists};
-
-use bytemuck::{AnyBitPattern, NoUninit};
-
-use crate::TypedConfig;
+use crate::{CancelToken, Config, Pyramid, Summary, TypedConfig};
 use crate::{Idx, IdxRange};
 
 use crate::pages::PagesRange;
 use crate::source::{SourceRange, TrySource};
 
-use super::{load_pages, pages_dir, store_pages_range, try_cache_pages, PagesDir, StoreError};
+use super::{
+    aggregate_range, external, get_item, get_items, level_end, load_level_range,
+    load_or_cache_pages, load_range, load_range_rev, load_range_strided, load_ranges, pages_dir,
+    resolve_range, untimed, Attached, Chunk, DenseFormat, Detach, Detached, GapError, PageFormat,
+    PagesDir, PagesStream, Plain, SourceBounds, SourcePolicy, StoreError, StoredAs, TaggedChunk,
+};
 
-#[async_trait]
-pub trait TryStore<'a, R, K, V>: TrySource<R, K, V>
+/// Caches the items of a [`TrySource`] in pages of the format `F`, which the item type picks through [`StoredAs`].
+pub trait TryStore<'a, R, K, V, F = Plain>: TrySource<R, K, V>
 where
     R: SourceRange + Send + 'a,
     K: Send + Sync + Copy + Hash + 'a,
-    V: NoUninit + AnyBitPattern + Send + Sync,
+    V: StoredAs<F> + Send + Sync + 'static,
+    F: PageFormat<V>,
 {
-    async fn load<const PAGE_SIZE: Idx>(
+    /// Loads the items of `r`, reading the cached pages and filling the others from the source.
+    /// A call that timed out, or that the breaker or the negative cache did not make, has no source error
+    /// to be yielded as, so the load ends there, as a [`Store`](crate::Store) load does: [`TryStore::try_load`] yields it.
+    /// A negative cache made [for the source](crate::NegativeCache::for_source) yields the error the range failed with instead.
+    /// The other store errors panic.
+    fn load<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, Result<V, Self::Error>>> + Send {
+        async move { external(load_items(self, k, r, config, Attached).await).boxed() }
+    }
+
+    /// Like [`TryStore::load`], but yields the store errors instead of panicking,
+    /// e.g. when the circuit breaker is open.
+    fn try_load<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, Result<V, StoreError<Self::Error>>>> + Send {
+        async move { load_items(self, k, r, config, Attached).await.boxed() }
+    }
+
+    /// Like [`TryStore::try_load`], but ends with a [`StoreError::Cancelled`] once `cancel` is cancelled,
+    /// the source call in progress being dropped.
+    fn try_load_cancellable<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+        cancel: &CancelToken,
+    ) -> impl Future<Output = BoxStream<'a, Result<V, StoreError<Self::Error>>>> + Send {
+        let config = config.cancelled_by(cancel);
+
+        async move { load_items(self, k, r, &config, Attached).await.boxed() }
+    }
+
+    /// Like [`TryStore::load`], but converts the store errors into the source error type instead of panicking,
+    /// for sources whose error implements `From<StoreError>`.
+    fn load_into<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, Result<V, Self::Error>>> + Send
+    where
+        Self::Error: From<StoreError>,
+    {
+        async move {
+            load_items(self, k, r, config, Attached)
+                .await
+                .map_err(StoreError::into_external)
+                .boxed()
+        }
+    }
+
+    /// Like [`TryStore::load`], but yields whole pages at once, each as a [`Chunk`] of values with their `IdxRange`.
+    /// Panics on an open range when the end of the data is not configured.
+    fn load_chunks<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, Result<Chunk<V>, Self::Error>>> + Send {
+        async move {
+            external(
+                self.load_degraded::<PAGE_SIZE>(k, r, config)
+                    .await
+                    .map_err(|gap| gap.error),
+            )
+            .boxed()
+        }
+    }
+
+    /// Like [`TryStore::load`], but yields the items in descending index order, from the end of `r`,
+    /// so that the newest ones are read first. Panics on an open range when the end of the data is not configured.
+    fn load_rev<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, Result<V, Self::Error>>> + Send {
+        async move {
+            match resolve_range(r, self.end(&k), config) {
+                Ok(Some(range)) => external(
+                    load_range_rev::<F, _, _, _, _, PAGE_SIZE>(
+                        pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                        range,
+                        move |pages| self.pages_source(k, pages),
+                        SourceBounds::new::<R, PAGE_SIZE>(config),
+                        config,
+                    )
+                    .await
+                    .map_ok(|chunk| stream::iter(chunk.values.into_iter().rev().map(Ok)))
+                    .map_err(|gap| gap.error)
+                    .try_flatten(),
+                )
+                .boxed(),
+                Ok(None) => stream::empty().boxed(),
+                Err(_) => panic!("{}", StoreError::<()>::Unbounded),
+            }
+        }
+    }
+
+    /// Loads every `step`-th item of `r`, from its start, reading only their slots from the cached pages.
+    /// The uncached pages are filled whole, so they are cached for the next loads.
+    /// Panics on an open range when the end of the data is not configured.
+    fn load_strided<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        step: NonZero<Idx>,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, Result<V, Self::Error>>> + Send
+    where
+        F: DenseFormat<V>,
+    {
+        async move {
+            match resolve_range(r, self.end(&k), config) {
+                Ok(Some(range)) => external(
+                    load_range_strided::<F, _, _, _, _, PAGE_SIZE>(
+                        pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                        range,
+                        step,
+                        move |pages| self.pages_source(k, pages),
+                        SourceBounds::new::<R, PAGE_SIZE>(config),
+                        config,
+                    )
+                    .await
+                    .map_err(|gap| gap.error),
+                )
+                .boxed(),
+                Ok(None) => stream::empty().boxed(),
+                Err(_) => panic!("{}", StoreError::<()>::Unbounded),
+            }
+        }
+    }
+
+    /// Loads several ranges in one go, yielding the chunks of each tagged with the range they belong to.
+    /// The overlapping and adjacent ranges are merged first, and the tags are the merged ranges.
+    fn load_ranges<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        ranges: impl IntoIterator<Item = IdxRange>,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, Result<TaggedChunk<V>, Self::Error>>> + Send {
+        let ranges = ranges
+            .into_iter()
+            .filter_map(|range| match config.end {
+                Some(end) => range.truncate(end),
+                None => Some(range),
+            })
+            .collect();
+
+        async move {
+            external(
+                load_ranges::<F, _, _, _, _, PAGE_SIZE>(
+                    pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                    ranges,
+                    move |pages| self.pages_source(k, pages),
+                    SourceBounds::new::<R, PAGE_SIZE>(config),
+                    config,
+                )
+                .await
+                .map_err(|gap| gap.error),
+            )
+            .boxed()
+        }
+    }
+
+    /// Like [`TryStore::load`], but the source call in progress when the stream is dropped
+    /// is finished in a background task, so the pages it was asked for still get cached.
+    fn load_detached<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, Result<V, Self::Error>>> + Send
+    where
+        Self::Stream: 'static,
+    {
+        async move { external(load_items(self, k, r, config, Detached).await).boxed() }
+    }
+
+    /// Like [`TryStore::load_detached`], but converts the store errors as [`TryStore::load_into`] does.
+    fn load_detached_into<const PAGE_SIZE: Idx>(
         &'a self,
         k: K,
         r: R,
-        config: &TypedConfig<V, PAGE_SIZE>,
-    ) -> BoxStream<'a, Result<V, Self::Error>>
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, Result<V, Self::Error>>> + Send
     where
-        'a: 'async_trait,
+        Self::Error: From<StoreError>,
+        Self::Stream: 'static,
     {
-        match r.try_into() {
-            Ok(range) => {
-                let dir = pages_dir(k, config);
-
-                let sealed = match try_exists(dir.as_ref()).await {
-                    Ok(exists) => {
-                        if exists {
-                            self.load_or_cache::<PAGE_SIZE>(dir, k, (&range).into())
-                                .boxed()
-                        } else {
-                            create_dir_all(dir.as_ref()).await.unwrap();
-
-                            let pages = (&range).into();
-                            let source = self.idx_range_source::<PAGE_SIZE>(k, range).await;
-
-                            try_cache_pages::<_, _, PAGE_SIZE>(dir, pages, source)
-                                .await
-                                .boxed()
-                        }
-                    }
-                    Err(err) => {
-                        panic!(
-                            "{}",
-                            StoreError::<()>::PathAccess(err, dir.as_ref().clone())
-                        );
-                    }
-                };
-
-                sealed
-                    .map(|result| match result {
-                        Ok(item) => Ok(stream::iter(item.into_iter().map(Ok))),
-                        Err(err) => match err {
-                            StoreError::External(err) => Err(err),
-                            err => panic!("{}", err),
-                        },
-                    })
-                    .try_flatten()
-                    .boxed()
+        async move {
+            load_items(self, k, r, config, Detached)
+                .await
+                .map_err(StoreError::into_external)
+                .boxed()
+        }
+    }
+
+    /// Degraded mode: yields every cached page of `r` even when the source fails,
+    /// each part of `r` that could not be loaded being reported as a [`GapError`].
+    fn load_degraded<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, Result<Chunk<V>, GapError<Self::Error>>>> + Send {
+        async move {
+            match resolve_range(r, self.end(&k), config) {
+                Ok(Some(range)) => self.load_idx_range::<PAGE_SIZE>(k, range, config).await,
+                Ok(None) => stream::empty().boxed(),
+                Err(_) => panic!("{}", StoreError::<()>::Unbounded),
+            }
+        }
+    }
+
+    fn load_idx_range<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        range: IdxRange,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = PagesStream<'a, V, Self::Error>> + Send {
+        async move {
+            load_range::<F, _, _, _, _, PAGE_SIZE>(
+                pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                range,
+                move |pages| self.pages_source(k, pages),
+                SourceBounds::new::<R, PAGE_SIZE>(config),
+                config,
+                Attached,
+            )
+            .await
+            .boxed()
+        }
+    }
+
+    /// Loads the item at `idx`, reading only its slot when its page is cached,
+    /// and asking the source for it alone otherwise.
+    fn get<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        idx: Idx,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = Result<Option<V>, Self::Error>> + Send
+    where
+        V: Clone,
+        F: DenseFormat<V>,
+    {
+        async move {
+            get_item::<F, V, Self::Error, _, _, PAGE_SIZE>(
+                pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                idx,
+                move |range| self.idx_range_source::<PAGE_SIZE>(k, range),
+                SourceBounds::new::<R, PAGE_SIZE>(config),
+                &untimed(config),
+            )
+            .await
+            .map_err(|err| match err {
+                StoreError::External(err) => err,
+                err => panic!("{}", err),
+            })
+        }
+    }
+
+    /// Loads the items at `idxs`, in order, with one read or source call per page they fall in,
+    /// `None` for those past the end of the data. Panics when the source yields fewer items than asked.
+    fn get_many<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        idxs: &[Idx],
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = Result<Vec<Option<V>>, Self::Error>> + Send
+    where
+        V: Clone,
+        F: DenseFormat<V>,
+    {
+        async move {
+            get_items::<F, V, Self::Error, _, _, PAGE_SIZE>(
+                pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                idxs,
+                move |range| self.idx_range_source::<PAGE_SIZE>(k, range),
+                SourceBounds::new::<R, PAGE_SIZE>(config),
+                &untimed(config),
+            )
+            .await
+            .map_err(|err| match err {
+                StoreError::External(err) => err,
+                err => panic!("{}", err),
+            })
+        }
+    }
+
+    /// Summarizes the items of `r` as `S`, reading the stored summaries of the full cached pages
+    /// and loading only the pages at the edges of `r`, or those not summarized yet.
+    /// Panics on an open range when the end of the data is not configured.
+    fn aggregate<S: Summary<V>, const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        r: R,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = Result<Option<S>, Self::Error>> + Send {
+        async move {
+            match resolve_range(r, self.end(&k), config) {
+                Ok(Some(range)) => aggregate_range::<F, _, _, _, _, _, PAGE_SIZE>(
+                    pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                    range,
+                    move |pages| self.pages_source(k, pages),
+                    SourceBounds::new::<R, PAGE_SIZE>(config),
+                    &untimed(config),
+                )
+                .await
+                .map_err(|gap| match gap.error {
+                    StoreError::External(err) => err,
+                    err => panic!("{}", err),
+                }),
+                Ok(None) => Ok(None),
+                Err(_) => panic!("{}", StoreError::<()>::Unbounded),
+            }
+        }
+    }
+
+    /// Loads `range` at `level` of the pyramid over the items, its indices being those of the level:
+    /// level 0 holds the items, and each value of level `l + 1` reduces `PAGE_SIZE` values of level `l`,
+    /// as `config` [reduces them](TypedConfig::reduce_levels), so `PAGE_SIZE^l` items.
+    /// The level pages are built from the cached pages on the first load and stored,
+    /// and built again once one of these pages is cached anew. Panics when `config` has no reduction.
+    fn load_level<const PAGE_SIZE: Idx>(
+        &'a self,
+        k: K,
+        range: IdxRange,
+        level: u32,
+        config: &TypedConfig<V, PAGE_SIZE, F>,
+    ) -> impl Future<Output = BoxStream<'a, Result<V, Self::Error>>> + Send
+    where
+        F: DenseFormat<V>,
+    {
+        let reduce = config
+            .pyramid
+            .as_ref()
+            .and_then(Pyramid::reduce)
+            .expect("a level load needs the levels reduction in the config");
+        let range = match level_end::<PAGE_SIZE>(config.end, level) {
+            Some(end) => range.truncate(end),
+            None => Some(range),
+        };
+
+        async move {
+            match range {
+                Some(range) => external(
+                    load_level_range::<F, _, _, _, _, PAGE_SIZE>(
+                        pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                        range,
+                        level,
+                        move |pages| self.pages_source(k, pages),
+                        SourceBounds::new::<R, PAGE_SIZE>(config),
+                        config,
+                        reduce,
+                    )
+                    .await
+                    .map_ok(|chunk| stream::iter(chunk.values.into_iter().map(Ok)))
+                    .map_err(|gap| gap.error)
+                    .try_flatten(),
+                )
+                .boxed(),
+                None => stream::empty().boxed(),
             }
-            Err(r) => self(k, r).await.boxed(),
         }
     }
 
@@ -81,25 +406,16 @@ where
         dir: PagesDir,
         k: K,
         pages: PagesRange<PAGE_SIZE>,
-    ) -> BoxStream<'a, Result<Vec<V>, StoreError<Self::Error>>> {
-        async_stream::try_stream! {
-            for await result in store_pages_range::<Self::Error, PAGE_SIZE>(dir.as_ref(), pages).await {
-                let store_pages = result?;
-
-                let pages_data = if store_pages.cached {
-                    load_pages::<V, Self::Error, PAGE_SIZE>(Arc::clone(&dir), store_pages.pages).boxed()
-                } else {
-                    let pages = store_pages.pages.clone();
-                    let source = self.pages_source(k, store_pages.pages).await;
-
-                    try_cache_pages::<_, _, PAGE_SIZE>(Arc::clone(&dir), pages, source)
-                        .await.boxed()
-                };
-
-                yield pages_data;
-            }
-        }
-        .try_flatten()
+        config: Config<PAGE_SIZE>,
+    ) -> PagesStream<'a, V, Self::Error> {
+        load_or_cache_pages::<F, _, _, _, _, PAGE_SIZE>(
+            dir,
+            pages,
+            move |pages| self.pages_source(k, pages),
+            SourceBounds::new::<R, PAGE_SIZE>(&config),
+            &config,
+            Attached,
+        )
         .boxed()
     }
 
@@ -119,21 +435,58 @@ where
         self.idx_range_source::<PAGE_SIZE>(k, idx_range)
     }
 
-    fn config<const PAGE_SIZE: Idx>(&self) -> TypedConfig<V, PAGE_SIZE> {
+    fn config<const PAGE_SIZE: Idx>(&self) -> TypedConfig<V, PAGE_SIZE, F> {
         TypedConfig::new()
     }
 }
 
-#[async_trait]
-impl<'a, F, R, K, V> TryStore<'a, R, K, V> for F
+impl<'a, S, R, K, V, F> TryStore<'a, R, K, V, F> for S
 where
-    F: TrySource<R, K, V>,
+    S: TrySource<R, K, V>,
     R: SourceRange + Send + 'a,
     K: Send + Sync + Copy + Hash + 'a,
-    V: NoUninit + AnyBitPattern + Send + Sync,
+    V: StoredAs<F> + Send + Sync + 'static,
+    F: PageFormat<V>,
 {
 }
 
+/// The items of `r`, along with the store errors, for the loads to box once.
+async fn load_items<'a, 's, S, R, K, V, F, const PAGE_SIZE: Idx>(
+    store: &'a S,
+    k: K,
+    r: R,
+    config: &TypedConfig<V, PAGE_SIZE, F>,
+    detach: impl Detach<'s>,
+) -> impl Stream<Item = Result<V, StoreError<S::Error>>> + Send + 'a
+where
+    's: 'a,
+    S: TryStore<'a, R, K, V, F> + ?Sized,
+    S::Stream: 's,
+    R: SourceRange + Send + 'a,
+    K: Send + Sync + Copy + Hash + 'a,
+    V: StoredAs<F> + Send + Sync + 'static,
+    F: PageFormat<V>,
+{
+    match resolve_range(r, store.end(&k), config) {
+        Ok(Some(range)) => Either::Left(
+            load_range::<F, _, _, _, _, PAGE_SIZE>(
+                pages_dir::<F, _, _, PAGE_SIZE>(k, config),
+                range,
+                move |pages| store.pages_source(k, pages),
+                SourceBounds::new::<R, PAGE_SIZE>(config),
+                config,
+                detach,
+            )
+            .await
+            .map_ok(|chunk| stream::iter(chunk.values.into_iter().map(Ok)))
+            .map_err(|gap| gap.error)
+            .try_flatten(),
+        ),
+        Ok(None) => Either::Right(stream::empty().boxed()),
+        Err(r) => Either::Right(SourcePolicy::new(config).call(store(k, r)).await),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::ops::Range;
@@ -172,4 +525,541 @@ mod tests {
 
         remove_dir_all(&config.root).await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_load_retry() {
+        use std::sync::Mutex;
+        use std::time::Duration;
+
+        use crate::RetryPolicy;
+
+        static CALLS: Mutex<Vec<Range<Idx>>> = Mutex::new(Vec::new());
+
+        async fn source(
+            err: &&'static str,
+            range: Range<Idx>,
+        ) -> BoxStream<'static, Result<Idx, &'static str>> {
+            CALLS.lock().unwrap().push(range.clone());
+
+            let err = *err;
+            let fails = range.start == 512;
+
+            stream::iter(range.map(move |idx| match idx {
+                2500 if fails => Err(err),
+                idx => Ok(idx),
+            }))
+            .boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.retry(
+            RetryPolicy::new(3)
+                .backoff(Duration::from_millis(1), Duration::from_millis(10))
+                .retry_if(|err: &&'static str| *err == "timeout"),
+        );
+
+        let values = source
+            .load::<PAGE_SIZE>(&"timeout", 512..4096, &config)
+            .await
+            .try_collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, Ok((512..4096).collect::<Vec<_>>()));
+        // the retry asks for the failed page alone, and the next call for the pages past it
+        assert_eq!(*CALLS.lock().unwrap(), [512..4096, 2048..3072, 3072..4096]);
+
+        let values = source
+            .load::<PAGE_SIZE>(&"fatal", 512..4096, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values.len(), 2048 - 512 + 1);
+        assert_eq!(values.last(), Some(&Err("fatal")));
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_retry_breaker() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        use crate::{CircuitBreaker, RetryPolicy};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Result<Idx, ()>> {
+            match CALLS.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => stream::iter([Err(())]).boxed(),
+                _ => stream::iter(range.map(Ok)).boxed(),
+            }
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.retry(RetryPolicy::for_source(&source, 3).backoff(Duration::ZERO, Duration::ZERO));
+        config.breaker = Some(Arc::new(CircuitBreaker::new(2, Duration::from_secs(60))));
+
+        // the failed attempts of a call that ends up succeeding don't open the breaker
+        let values = source
+            .try_load::<PAGE_SIZE>(&(), 0..1024, &config)
+            .await
+            .map_err(|err| err.to_string())
+            .try_collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, Ok((0..1024).collect::<Vec<_>>()));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 3);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_try_load_breaker() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        use crate::{CircuitBreaker, NegativeCache};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Result<Idx, Idx>> {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+
+            stream::iter([Err(range.start)]).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.negative_cache = Some(Arc::new(NegativeCache::new(Duration::from_secs(60))));
+
+        let loads = [0..2048, 1024..1500, 4096..5120 as Idx]
+            .map(|range| source.try_load::<PAGE_SIZE>(&(), range, &config));
+
+        let mut values = Vec::new();
+
+        for load in loads {
+            values.push(
+                load.await
+                    .map_err(|err| err.to_string())
+                    .collect::<Vec<_>>()
+                    .await,
+            );
+        }
+
+        assert_eq!(
+            values,
+            [
+                vec![Err(StoreError::External(0).to_string())],
+                vec![Err(StoreError::<Idx>::RecentFailure(0, 2047).to_string())],
+                vec![Err(StoreError::External(4096).to_string())],
+            ]
+        );
+        assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+
+        config.negative_cache = None;
+        config.breaker = Some(Arc::new(CircuitBreaker::new(2, Duration::from_secs(60))));
+
+        for range in [8192..9216, 9216..10240 as Idx] {
+            let values = source
+                .try_load::<PAGE_SIZE>(&(), range, &config)
+                .await
+                .collect::<Vec<_>>()
+                .await;
+
+            assert_eq!(values.len(), 1);
+        }
+
+        let values = source
+            .try_load::<PAGE_SIZE>(&(), 0..1024, &config)
+            .await
+            .map_err(|err| err.to_string())
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, [Err(StoreError::<Idx>::CircuitOpen(2).to_string())]);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 4);
+
+        // with no source error for an open breaker, the load ends there
+        let values = source
+            .load::<PAGE_SIZE>(&(), 0..1024, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert!(values.is_empty());
+
+        // a negative cache made for the source fails the loads with the error the range failed with
+        config.breaker = None;
+        config.negative_cache = Some(Arc::new(NegativeCache::for_source(
+            &source,
+            Duration::from_secs(60),
+        )));
+
+        for _ in 0..2 {
+            let values = source
+                .load::<PAGE_SIZE>(&(), 12288..13312, &config)
+                .await
+                .collect::<Vec<_>>()
+                .await;
+
+            assert_eq!(values, [Err(12288)]);
+        }
+
+        assert_eq!(CALLS.load(Ordering::SeqCst), 5);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_into() {
+        use std::ops::RangeFrom;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        use crate::NegativeCache;
+
+        #[derive(Debug, PartialEq)]
+        enum TestError {
+            Source(&'static str),
+            Store(String),
+        }
+
+        impl From<StoreError> for TestError {
+            fn from(err: StoreError) -> Self {
+                TestError::Store(err.to_string())
+            }
+        }
+
+        async fn source(_k: &(), _r: RangeFrom<Idx>) -> BoxStream<'static, Result<Idx, TestError>> {
+            stream::iter([Err(TestError::Source("down"))]).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.end = Some(1024);
+        config.negative_cache = Some(Arc::new(NegativeCache::new(Duration::from_secs(60))));
+
+        let mut values = Vec::new();
+
+        for _ in 0..2 {
+            values.push(
+                source
+                    .load_into::<PAGE_SIZE>(&(), 0.., &config)
+                    .await
+                    .collect::<Vec<_>>()
+                    .await,
+            );
+        }
+
+        assert_eq!(
+            values,
+            [
+                vec![Err(TestError::Source("down"))],
+                vec![Err(TestError::Store(
+                    StoreError::<()>::RecentFailure(0, 1023).to_string()
+                ))],
+            ]
+        );
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_degraded() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        static DOWN: AtomicBool = AtomicBool::new(false);
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Result<Idx, Idx>> {
+            match DOWN.load(Ordering::SeqCst) {
+                true => stream::iter([Err(range.start)]).boxed(),
+                false => stream::iter(range.map(Ok)).boxed(),
+            }
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        for range in [0..1024, 3072..4096 as Idx] {
+            source
+                .load::<PAGE_SIZE>(&(), range, &config)
+                .await
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap();
+        }
+
+        DOWN.store(true, Ordering::SeqCst);
+
+        let chunks = source
+            .load_degraded::<PAGE_SIZE>(&(), 512..4096, &config)
+            .await
+            .map_err(|gap| (gap.range, gap.error.to_string()))
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(
+            chunks,
+            [
+                Ok(Chunk::new(
+                    IdxRange::new(512, 512).unwrap(),
+                    (512..1024).collect()
+                )),
+                Err((
+                    IdxRange::new(1024, 2048).unwrap(),
+                    StoreError::External(1024).to_string()
+                )),
+                Ok(Chunk::new(
+                    IdxRange::new(3072, 1024).unwrap(),
+                    (3072..4096).collect()
+                )),
+            ]
+        );
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_try_load_timeouts() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Mutex;
+        use std::time::Duration;
+
+        use crate::CancelToken;
+
+        const NORMAL: usize = 0;
+        const SLOW_CALL: usize = 1;
+        const HUNG_ITEM: usize = 2;
+
+        static MODE: AtomicUsize = AtomicUsize::new(NORMAL);
+        static CALLS: Mutex<Vec<Range<Idx>>> = Mutex::new(Vec::new());
+
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Result<Idx, ()>> {
+            CALLS.lock().unwrap().push(range.clone());
+
+            match MODE.load(Ordering::SeqCst) {
+                SLOW_CALL => {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    stream::empty().boxed()
+                }
+                HUNG_ITEM => stream::iter((range.start..1500).map(Ok))
+                    .chain(stream::pending())
+                    .boxed(),
+                _ => stream::iter(range.map(Ok)).boxed(),
+            }
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+        config.call_timeout = Some(Duration::from_millis(50));
+        config.item_timeout = Some(Duration::from_millis(50));
+
+        async fn try_load(config: &TypedConfig<Idx, PAGE_SIZE>) -> Vec<Result<Idx, String>> {
+            source
+                .try_load::<PAGE_SIZE>(&(), 0..2048, config)
+                .await
+                .map_err(|err| err.to_string())
+                .collect()
+                .await
+        }
+
+        MODE.store(SLOW_CALL, Ordering::SeqCst);
+
+        let call_timeout = StoreError::<()>::CallTimeout(Duration::from_millis(50));
+        assert_eq!(try_load(&config).await, [Err(call_timeout.to_string())]);
+
+        MODE.store(HUNG_ITEM, Ordering::SeqCst);
+
+        let values = try_load(&config).await;
+        let item_timeout = StoreError::<()>::ItemTimeout(Duration::from_millis(50));
+
+        assert_eq!(values.len(), 1025);
+        assert_eq!(values[1024], Err(item_timeout.to_string()));
+
+        // with no source error for the timeout, the load ends there
+        let values = source
+            .load::<PAGE_SIZE>(&(), 0..2048, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values, (0..1024).map(Ok).collect::<Vec<_>>());
+
+        config.item_timeout = None;
+
+        let cancel = CancelToken::new();
+        tokio::spawn({
+            let cancel = cancel.clone();
+
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                cancel.cancel();
+            }
+        });
+
+        let values = source
+            .try_load_cancellable::<PAGE_SIZE>(&(), 0..2048, &config, &cancel)
+            .await
+            .map_err(|err| err.to_string())
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values.len(), 1025);
+        assert_eq!(values[1023], Ok(1023));
+        assert_eq!(values[1024], Err(StoreError::<()>::Cancelled.to_string()));
+
+        MODE.store(NORMAL, Ordering::SeqCst);
+        CALLS.lock().unwrap().clear();
+
+        assert_eq!(
+            try_load(&config).await,
+            (0..2048).map(Ok).collect::<Vec<_>>()
+        );
+        assert_eq!(CALLS.lock().unwrap().pop(), Some(1024..2048));
+        assert!(CALLS.lock().unwrap().is_empty());
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_codec() {
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Result<Vec<u16>, ()>> {
+            stream::iter(range.map(|i| Ok(vec![i as u16; i as usize % 5]))).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        let range = 512..4096 as Idx;
+
+        let values_1 = source
+            .load::<PAGE_SIZE>(&(), range.clone(), &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let values_2 = source
+            .load::<PAGE_SIZE>(&(), range, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values_1, values_2);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_nullable() {
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Result<Option<Idx>, ()>> {
+            stream::iter(range.map(|i| Ok((i % 5 == 0).then_some(i)))).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        let range = 512..4096 as Idx;
+
+        let values_1 = source
+            .load::<PAGE_SIZE>(&(), range.clone(), &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let values_2 = source
+            .load::<PAGE_SIZE>(&(), range, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values_1, values_2);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_sparse() {
+        async fn source(_k: &(), range: Range<Idx>) -> BoxStream<'static, Result<(Idx, Idx), ()>> {
+            stream::iter(range.filter(|i| i % 1000 < 3).map(|i| Ok((i, i)))).boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        let range = 512..4096 as Idx;
+
+        let values_1 = source
+            .load::<PAGE_SIZE>(&(), range.clone(), &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let values_2 = source
+            .load::<PAGE_SIZE>(&(), range, &config)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(values_1, values_2);
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_sparse_misplaced() {
+        async fn source(
+            misplaced: &Idx,
+            range: Range<Idx>,
+        ) -> BoxStream<'static, Result<(Idx, Idx), ()>> {
+            stream::iter([range.start + 100, *misplaced, range.start + 200].map(|i| Ok((i, i))))
+                .boxed()
+        }
+
+        const PAGE_SIZE: Idx = 1024;
+
+        let mut config = source.config::<PAGE_SIZE>();
+        config.root = PathBuf::from(format!("{}", rand::random::<u128>())).into();
+
+        // out of order, then before the range
+        for misplaced in [50 as Idx, 5] {
+            for _ in 0..2 {
+                let values = source
+                    .try_load::<PAGE_SIZE>(&misplaced, 0..4096, &config)
+                    .await
+                    .map_err(|err| err.to_string())
+                    .collect::<Vec<_>>()
+                    .await;
+
+                assert_eq!(
+                    values,
+                    [Err(StoreError::<()>::SourceIndex(0, misplaced).to_string())]
+                );
+            }
+        }
+
+        remove_dir_all(&config.root).await.unwrap();
+    }
 }
@@ -0,0 +1,126 @@
+use std::any::{Any, TypeId};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use bytemuck::{AnyBitPattern, NoUninit};
+
+/// A monoid over the items, e.g. their min, max, sum or count, kept for every full page.
+///
+/// The summary of a range is the combination, in order, of the summaries of its items.
+pub trait Summary<V>: NoUninit + AnyBitPattern + Send + Sync {
+    /// The summary of a single item.
+    fn of(item: &V) -> Self;
+
+    /// Combines the summary of some items with the summary of the items following them.
+    fn combine(self, next: Self) -> Self;
+}
+
+/// The summary of `items`, `None` when there are none.
+pub(crate) fn summarize<V, S: Summary<V>>(items: &[V]) -> Option<S> {
+    items.iter().map(S::of).reduce(S::combine)
+}
+
+type Summarize<V> = fn(&[V]) -> Option<Vec<u8>>;
+
+/// Summarizes the full pages as they are cached, storing the summary next to each page.
+///
+/// The summary type is erased, so it may be set in an untyped [`Config`](crate::Config);
+/// its summaries are stored under its name, so another summary type never reads them.
+#[derive(Clone)]
+pub struct PageSummary {
+    summary: TypeId,
+    name: Arc<str>,
+    summarize: Arc<dyn Any + Send + Sync>,
+}
+
+impl PageSummary {
+    pub fn new<V: 'static, S: Summary<V>>() -> Self {
+        let summarize: Summarize<V> =
+            |items| summarize::<V, S>(items).map(|summary| bytemuck::bytes_of(&summary).to_vec());
+
+        Self {
+            summary: TypeId::of::<S>(),
+            name: name::<S>().into(),
+            summarize: Arc::new(summarize),
+        }
+    }
+
+    /// Whether the stored summaries are of type `S`.
+    pub(crate) fn is<S: 'static>(&self) -> bool {
+        self.summary == TypeId::of::<S>()
+    }
+
+    /// The name of the summary type, as it may appear in a file name.
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The bytes of the summary of `items`, `None` when the summary isn't over `V`.
+    pub(crate) fn summarize<V: 'static>(&self, items: &[V]) -> Option<Vec<u8>> {
+        self.summarize
+            .downcast_ref::<Summarize<V>>()
+            .and_then(|summarize| summarize(items))
+    }
+}
+
+/// The path of `S` with anything but alphanumerics and underscores replaced, e.g. `my_crate_MinMax`.
+fn name<S>() -> String {
+    std::any::type_name::<S>()
+        .replace("::", "_")
+        .replace(|c: char| !c.is_ascii_alphanumeric() && c != '_', "-")
+}
+
+impl Hash for PageSummary {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.summary.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::{Pod, Zeroable};
+
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+    #[repr(C)]
+    struct MinMax {
+        min: u32,
+        max: u32,
+    }
+
+    impl Summary<u32> for MinMax {
+        fn of(item: &u32) -> Self {
+            Self {
+                min: *item,
+                max: *item,
+            }
+        }
+
+        fn combine(self, next: Self) -> Self {
+            Self {
+                min: self.min.min(next.min),
+                max: self.max.max(next.max),
+            }
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(
+            summarize::<_, MinMax>(&[5, 2, 9, 4]),
+            Some(MinMax { min: 2, max: 9 })
+        );
+        assert_eq!(summarize::<_, MinMax>(&[]), None);
+
+        let summary = PageSummary::new::<u32, MinMax>();
+
+        assert!(summary.is::<MinMax>());
+        assert_eq!(summary.name(), "cachalot_summary_tests_MinMax");
+        assert_eq!(
+            summary.summarize(&[5u32, 2, 9]),
+            Some(bytemuck::bytes_of(&MinMax { min: 2, max: 9 }).to_vec())
+        );
+        assert_eq!(summary.summarize(&[5u64]), None);
+    }
+}
@@ -0,0 +1,233 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Idx, IndexDomain};
+
+pub const MINUTE: Duration = Duration::from_secs(60);
+pub const HOUR: Duration = Duration::from_secs(60 * 60);
+pub const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Resolution and origin of a timestamp index: index `i` stands for `ORIGIN + i * TICK`.
+///
+/// The scale is fixed and in UTC, with no time zones, daylight saving or leap seconds,
+/// so pages of a fixed number of ticks span fixed durations from the origin: UTC hours or days,
+/// but not local days or calendar months.
+pub trait TimeScale: 'static {
+    const TICK: Duration;
+
+    /// Nanoseconds from the UNIX epoch, negative for earlier origins.
+    const ORIGIN: i128 = 0;
+}
+
+pub struct Nanos;
+
+impl TimeScale for Nanos {
+    const TICK: Duration = Duration::from_nanos(1);
+}
+
+pub struct Millis;
+
+impl TimeScale for Millis {
+    const TICK: Duration = Duration::from_millis(1);
+}
+
+pub struct Secs;
+
+impl TimeScale for Secs {
+    const TICK: Duration = Duration::from_secs(1);
+}
+
+pub struct Minutes;
+
+impl TimeScale for Minutes {
+    const TICK: Duration = MINUTE;
+}
+
+/// How many ticks of `S` make `span` - e.g. `ticks::<Secs>(HOUR)` as a page size gives one page per hour.
+pub const fn ticks<S: TimeScale>(span: Duration) -> Idx {
+    span.as_nanos() / S::TICK.as_nanos()
+}
+
+/// A point in time, indexed on the `S` scale.
+pub struct Timestamp<S> {
+    nanos: i128,
+    _scale: PhantomData<S>,
+}
+
+impl<S: TimeScale> Timestamp<S> {
+    pub fn from_nanos(nanos: i128) -> Self {
+        Self {
+            nanos,
+            _scale: PhantomData,
+        }
+    }
+
+    /// Nanoseconds from the UNIX epoch.
+    pub fn nanos(&self) -> i128 {
+        self.nanos
+    }
+
+    /// The time of the timestamp, `None` past the range of `SystemTime`.
+    pub fn time(&self) -> Option<SystemTime> {
+        let nanos = self.nanos.unsigned_abs();
+        let offset = Duration::new(
+            u64::try_from(nanos / 1_000_000_000).ok()?,
+            (nanos % 1_000_000_000) as u32,
+        );
+
+        if self.nanos < 0 {
+            UNIX_EPOCH.checked_sub(offset)
+        } else {
+            UNIX_EPOCH.checked_add(offset)
+        }
+    }
+}
+
+impl<S: TimeScale> From<SystemTime> for Timestamp<S> {
+    fn from(time: SystemTime) -> Self {
+        Self::from_nanos(match time.duration_since(UNIX_EPOCH) {
+            Ok(after) => after.as_nanos() as i128,
+            Err(before) => -(before.duration().as_nanos() as i128),
+        })
+    }
+}
+
+impl<S: TimeScale> IndexDomain for Timestamp<S> {
+    /// Times are floored to the tick, times before the origin have no index.
+    fn to_idx(self) -> Option<Idx> {
+        let ticks = (self.nanos - S::ORIGIN).div_euclid(S::TICK.as_nanos() as i128);
+
+        Idx::try_from(ticks).ok()
+    }
+
+    /// Times past the range of `i128` nanoseconds saturate.
+    fn from_idx(idx: Idx) -> Self {
+        let nanos = idx.saturating_mul(S::TICK.as_nanos());
+
+        Self::from_nanos(S::ORIGIN.saturating_add(i128::try_from(nanos).unwrap_or(i128::MAX)))
+    }
+}
+
+impl<S> Clone for Timestamp<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for Timestamp<S> {}
+
+impl<S> PartialEq for Timestamp<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.nanos == other.nanos
+    }
+}
+
+impl<S> Eq for Timestamp<S> {}
+
+impl<S> PartialOrd for Timestamp<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S> Ord for Timestamp<S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.nanos.cmp(&other.nanos)
+    }
+}
+
+impl<S> Hash for Timestamp<S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.nanos.hash(state)
+    }
+}
+
+impl<S> fmt::Debug for Timestamp<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Timestamp").field(&self.nanos).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::IdxRange;
+
+    struct Before1900;
+
+    impl TimeScale for Before1900 {
+        const TICK: Duration = DAY;
+        const ORIGIN: i128 = -2_208_988_800 * 1_000_000_000;
+    }
+
+    #[test]
+    fn test_index() {
+        let t = Timestamp::<Secs>::from(UNIX_EPOCH + Duration::from_millis(90_500));
+        assert_eq!(t.to_idx(), Some(90));
+        assert_eq!(
+            Timestamp::<Millis>::from(t.time().unwrap()).to_idx(),
+            Some(90_500)
+        );
+        assert_eq!(
+            Timestamp::<Minutes>::from(t.time().unwrap()).to_idx(),
+            Some(1)
+        );
+
+        let t = Timestamp::<Secs>::from_idx(90);
+        assert_eq!(t.time(), Some(UNIX_EPOCH + Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn test_origin() {
+        let t = Timestamp::<Before1900>::from(UNIX_EPOCH - DAY * 365);
+        assert_eq!(t.to_idx(), Some(70 * 365 + 17 - 365));
+        assert_eq!(t.to_idx().map(Timestamp::<Before1900>::from_idx), Some(t));
+    }
+
+    #[test]
+    fn test_before_origin() {
+        let before = Timestamp::<Secs>::from(UNIX_EPOCH - Duration::from_secs(1));
+
+        assert_eq!(before.to_idx(), None);
+        assert!(IdxRange::try_from(before..Timestamp::from(UNIX_EPOCH + HOUR)).is_err());
+        assert_eq!(
+            Timestamp::<Secs>::from_idx(Idx::MAX),
+            Timestamp::from_nanos(i128::MAX)
+        );
+    }
+
+    #[test]
+    fn test_time_range() {
+        assert_eq!(Timestamp::<Secs>::from_idx(Idx::MAX).time(), None);
+        assert_eq!(Timestamp::<Secs>::from_nanos(i128::MIN).time(), None);
+        assert_eq!(
+            Timestamp::<Secs>::from_nanos(-1_500_000_000).time(),
+            Some(UNIX_EPOCH - Duration::from_millis(1500))
+        );
+    }
+
+    #[test]
+    fn test_hour_pages() {
+        const PAGE_SIZE: Idx = ticks::<Secs>(HOUR);
+
+        let t0 = Timestamp::<Secs>::from(UNIX_EPOCH + DAY + HOUR * 2 + MINUTE * 30);
+        let t1 = Timestamp::<Secs>::from(UNIX_EPOCH + DAY + HOUR * 4);
+
+        let range = IdxRange::try_from(t0..t1).unwrap();
+        let mut pages = range.pages::<PAGE_SIZE>();
+
+        assert_eq!(
+            pages.next().map(|page| (page.page, page.first)),
+            Some((26, 1800))
+        );
+        assert_eq!(
+            pages.next().map(|page| (page.page, page.len())),
+            Some((27, 3600))
+        );
+        assert!(pages.next().is_none());
+    }
+}
@@ -0,0 +1,140 @@
+use std::borrow::Cow;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Semaphore;
+use tokio::task;
+
+use crate::StoreError;
+
+/// Persists the fetched pages in the background, so the loads yield them before they reach the disk.
+///
+/// At most `queue` pages wait to be written at once, the next ones waiting for room in the queue.
+/// A page only becomes visible to the loads once fully written; call [`WriteBehind::flush`]
+/// before the process exits so none is lost.
+pub struct WriteBehind {
+    queue: usize,
+    permits: Arc<Semaphore>,
+    writes: AtomicU64,
+    failure: Arc<Mutex<Option<StoreError>>>,
+}
+
+impl WriteBehind {
+    pub const DEFAULT_QUEUE: usize = 64;
+
+    pub fn new(queue: usize) -> Self {
+        let queue = queue.max(1);
+
+        Self {
+            queue,
+            permits: Arc::new(Semaphore::new(queue)),
+            writes: AtomicU64::new(0),
+            failure: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Waits for room in the queue, then writes `files` in order in the background,
+    /// stopping at the first failure so a file is only written after the ones before it.
+    pub(crate) async fn write(&self, files: Vec<(Cow<'static, Path>, Vec<u8>)>) {
+        let permit = Arc::clone(&self.permits).acquire_owned().await.unwrap();
+        let failure = Arc::clone(&self.failure);
+
+        let write = self.writes.fetch_add(1, Ordering::Relaxed);
+
+        task::spawn_blocking(move || {
+            let _permit = permit;
+
+            let written = files.into_iter().try_for_each(|(path, bytes)| {
+                let temp = path.with_extension(format!("{}.tmp", write));
+
+                fs::write(&temp, bytes)
+                    .map_err(|err| StoreError::FileCreation(err, temp.clone().into()))
+                    .and_then(|_| {
+                        fs::rename(&temp, &path)
+                            .map_err(|err| StoreError::FileCreation(err, path.clone()))
+                    })
+                    .inspect_err(|_| {
+                        let _ = fs::remove_file(&temp);
+                    })
+            });
+
+            if let Err(err) = written {
+                failure.lock().unwrap().get_or_insert(err);
+            }
+        });
+    }
+
+    /// Waits until every queued page is written, returning the first write that failed since the last flush.
+    pub async fn flush(&self) -> Result<(), StoreError> {
+        let permits = self.permits.acquire_many(self.queue as u32).await.unwrap();
+        drop(permits);
+
+        match self.failure.lock().unwrap().take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for WriteBehind {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_QUEUE)
+    }
+}
+
+impl Hash for WriteBehind {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.queue.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_flush() {
+        let dir = PathBuf::from(format!("{}", rand::random::<u128>()));
+        fs::create_dir(&dir).unwrap();
+
+        let writer = WriteBehind::new(2);
+
+        for page in 0..5 {
+            let path = dir.join(format!("{}", page));
+
+            writer
+                .write(vec![(path.into(), vec![page as u8; 16])])
+                .await;
+        }
+
+        writer.flush().await.unwrap();
+
+        for page in 0..5 {
+            assert_eq!(fs::read(dir.join(format!("{}", page))).unwrap(), [page; 16]);
+        }
+
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 5);
+
+        // the files after a failed one are not written
+        writer
+            .write(vec![
+                (dir.join("missing").join("0").into(), vec![]),
+                (dir.join("5").into(), vec![]),
+            ])
+            .await;
+
+        assert!(matches!(
+            writer.flush().await,
+            Err(StoreError::FileCreation(..))
+        ));
+        assert!(writer.flush().await.is_ok());
+        assert!(!dir.join("5").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
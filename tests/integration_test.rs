@@ -116,3 +116,545 @@ async fn complex_test() {
 
     remove_dir_all(".tests_complex_test").await.unwrap()
 }
+
+#[tokio::test]
+async fn codec_store() {
+    use std::ops::Range;
+    use std::sync::{Arc, LazyLock};
+
+    use futures::stream::{self, Stream, StreamExt};
+
+    use tokio::fs::remove_dir_all;
+
+    use cachalot::{cachalot, WriteBehind};
+
+    static WRITER: LazyLock<Arc<WriteBehind>> = LazyLock::new(Default::default);
+
+    fn writer() -> Arc<WriteBehind> {
+        Arc::clone(&WRITER)
+    }
+
+    #[cachalot(
+        root = ".tests_codec_store",
+        codec,
+        max_source_range = 3000,
+        write_behind = "writer"
+    )]
+    async fn source(_key: &'static str, range: Range<u128>) -> impl Stream<Item = String> {
+        stream::iter(range).map(|i| format!("item #{}", i))
+    }
+
+    let expected = (1600..8000)
+        .map(|i| format!("item #{}", i))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        source("str", 1600..8000).await.collect::<Vec<_>>().await,
+        expected
+    );
+
+    WRITER.flush().await.unwrap();
+
+    assert_eq!(
+        source("str", 1600..8000).await.collect::<Vec<_>>().await,
+        expected
+    );
+
+    remove_dir_all(".tests_codec_store").await.unwrap()
+}
+
+#[tokio::test]
+async fn nullable_store() {
+    use std::ops::Range;
+
+    use futures::stream::{self, Stream, StreamExt};
+
+    use tokio::fs::remove_dir_all;
+
+    use cachalot::cachalot;
+
+    #[cachalot(root = ".tests_nullable_store", nullable)]
+    async fn source(_key: &'static str, range: Range<u128>) -> impl Stream<Item = Option<f64>> {
+        stream::iter(range).map(|i| (i % 60 < 30).then_some(i as f64 / 2.0))
+    }
+
+    let expected = (1600..8000)
+        .map(|i| (i % 60 < 30).then_some(i as f64 / 2.0))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        source("trades", 1600..8000).await.collect::<Vec<_>>().await,
+        expected
+    );
+    assert_eq!(
+        source("trades", 1600..8000).await.collect::<Vec<_>>().await,
+        expected
+    );
+
+    remove_dir_all(".tests_nullable_store").await.unwrap()
+}
+
+#[tokio::test]
+async fn sparse_store() {
+    use std::ops::Range;
+
+    use futures::stream::{self, Stream, StreamExt};
+
+    use tokio::fs::remove_dir_all;
+
+    use cachalot::cachalot;
+
+    #[cachalot(root = ".tests_sparse_store", sparse)]
+    async fn source(_key: &'static str, range: Range<u128>) -> impl Stream<Item = (u128, u64)> {
+        stream::iter(range)
+            .filter(|i| futures::future::ready(i % 997 == 0))
+            .map(|i| (i, i as u64))
+    }
+
+    let range = 10_000..1_000_000;
+    let expected = range
+        .clone()
+        .filter(|i| i % 997 == 0)
+        .map(|i| (i, i as u64))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        source("events", range.clone())
+            .await
+            .collect::<Vec<_>>()
+            .await,
+        expected
+    );
+    assert_eq!(
+        source("events", range).await.collect::<Vec<_>>().await,
+        expected
+    );
+
+    remove_dir_all(".tests_sparse_store").await.unwrap()
+}
+
+#[tokio::test]
+async fn bounded_store() {
+    use std::ops::RangeFrom;
+
+    use futures::stream::{self, Stream, StreamExt};
+
+    use tokio::fs::remove_dir_all;
+
+    use cachalot::cachalot;
+
+    fn today(_key: &'static str) -> u128 {
+        5000
+    }
+
+    #[cachalot(root = ".tests_bounded_store", end = "today")]
+    async fn source(key: &'static str, range: RangeFrom<u128>) -> impl Stream<Item = u128> {
+        stream::iter(range.start..today(key)).map(|i| i * 3)
+    }
+
+    let expected = (1600..5000).map(|i| i * 3).collect::<Vec<_>>();
+
+    assert_eq!(
+        source("x3", 1600..).await.collect::<Vec<_>>().await,
+        expected
+    );
+    assert_eq!(
+        source("x3", 1600..).await.collect::<Vec<_>>().await,
+        expected
+    );
+
+    remove_dir_all(".tests_bounded_store").await.unwrap()
+}
+
+#[tokio::test]
+async fn signed_store() {
+    use std::ops::Range;
+
+    use futures::stream::{self, Stream, StreamExt};
+
+    use tokio::fs::remove_dir_all;
+
+    use cachalot::cachalot;
+
+    #[cachalot(root = ".tests_signed_store", detach)]
+    async fn source(_key: &'static str, range: Range<i64>) -> impl Stream<Item = i64> {
+        stream::iter(range).map(|i| i * 10)
+    }
+
+    let expected = (-5000..3000).map(|i| i * 10).collect::<Vec<_>>();
+
+    assert_eq!(
+        source("x10", -5000..3000).await.collect::<Vec<_>>().await,
+        expected
+    );
+    assert_eq!(
+        source("x10", -5000..3000).await.collect::<Vec<_>>().await,
+        expected
+    );
+    assert_eq!(
+        source("x10", -1500..-1000).await.collect::<Vec<_>>().await,
+        (-1500..-1000).map(|i| i * 10).collect::<Vec<_>>()
+    );
+
+    remove_dir_all(".tests_signed_store").await.unwrap()
+}
+
+#[tokio::test]
+async fn summary_store() {
+    use std::ops::Range;
+
+    use bytemuck::{Pod, Zeroable};
+
+    use futures::stream::{self, Stream, StreamExt};
+
+    use tokio::fs::{read_dir, remove_dir_all};
+
+    use cachalot::{cachalot, Summary};
+
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    #[repr(C)]
+    struct Max(f64);
+
+    impl Summary<f64> for Max {
+        fn of(item: &f64) -> Self {
+            Max(*item)
+        }
+
+        fn combine(self, next: Self) -> Self {
+            Max(self.0.max(next.0))
+        }
+    }
+
+    #[cachalot(root = ".tests_summary_store", summary = "Max")]
+    async fn source(_key: &'static str, range: Range<u128>) -> impl Stream<Item = f64> {
+        stream::iter(range).map(|i| i as f64 / 2.0)
+    }
+
+    assert_eq!(
+        source("half", 1600..8000).await.collect::<Vec<_>>().await,
+        (1600..8000).map(|i| i as f64 / 2.0).collect::<Vec<_>>()
+    );
+
+    let mut dirs = read_dir(".tests_summary_store").await.unwrap();
+    let mut summaries = 0;
+
+    while let Some(dir) = dirs.next_entry().await.unwrap() {
+        let mut pages = read_dir(dir.path()).await.unwrap();
+
+        while let Some(page) = pages.next_entry().await.unwrap() {
+            if page.path().extension().is_some_and(|ext| ext == "summary") {
+                summaries += 1;
+            }
+        }
+    }
+
+    // pages 2 to 6, the partial ones at the edges aren't cached
+    assert_eq!(summaries, 5);
+
+    remove_dir_all(".tests_summary_store").await.unwrap()
+}
+
+#[tokio::test]
+async fn time_store() {
+    use std::ops::Range;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use futures::stream::{self, Stream, StreamExt};
+
+    use tokio::fs::remove_dir_all;
+
+    use cachalot::time::{Secs, Timestamp, HOUR};
+    use cachalot::{cachalot, IndexDomain};
+
+    #[cachalot(root = ".tests_time_store", page_size = 3600)]
+    async fn source(_key: &'static str, range: Range<Timestamp<Secs>>) -> impl Stream<Item = u64> {
+        stream::iter(range.start.to_idx().unwrap()..range.end.to_idx().unwrap())
+            .map(|secs| secs as u64 % 60)
+    }
+
+    let t0 = UNIX_EPOCH + HOUR * 100 + Duration::from_secs(1234);
+    let t1 = t0 + HOUR * 3;
+
+    let expected = (0..3 * 3600)
+        .map(|secs| (secs + 34) % 60)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        source("seconds", t0.into()..t1.into())
+            .await
+            .collect::<Vec<_>>()
+            .await,
+        expected
+    );
+    assert_eq!(
+        source("seconds", t0.into()..t1.into())
+            .await
+            .collect::<Vec<_>>()
+            .await,
+        expected
+    );
+
+    remove_dir_all(".tests_time_store").await.unwrap()
+}
+
+#[tokio::test]
+async fn breaker_store() {
+    use std::ops::Range;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::stream::{self, Stream, StreamExt};
+
+    use tokio::fs::remove_dir_all;
+
+    use cachalot::{cachalot, StoreError};
+
+    #[derive(Debug, PartialEq)]
+    pub enum MyError {
+        Down,
+        Store(String),
+    }
+
+    impl From<StoreError> for MyError {
+        fn from(err: StoreError) -> Self {
+            MyError::Store(err.to_string())
+        }
+    }
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    #[cachalot(root = ".tests_breaker_store", breaker = 2, negative_cache_ms = 60000)]
+    async fn source(
+        _key: &'static str,
+        _range: Range<u128>,
+    ) -> impl Stream<Item = Result<u128, MyError>> {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+
+        stream::iter([Err(MyError::Down)])
+    }
+
+    let mut values = Vec::new();
+
+    for range in [0..1024, 0..1024, 1024..2048, 2048..3072] {
+        values.push(source("down", range).await.collect::<Vec<_>>().await);
+    }
+
+    assert_eq!(
+        values,
+        [
+            vec![Err(MyError::Down)],
+            vec![Err(MyError::Store(
+                StoreError::<()>::RecentFailure(0, 1023).to_string()
+            ))],
+            vec![Err(MyError::Down)],
+            vec![Err(MyError::Store(
+                StoreError::<()>::CircuitOpen(2).to_string()
+            ))],
+        ]
+    );
+    assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+
+    remove_dir_all(".tests_breaker_store").await.unwrap()
+}
+
+#[tokio::test]
+async fn prefetch_store() {
+    use std::ops::Range;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use futures::stream::{self, Stream, StreamExt};
+
+    use tokio::fs::remove_dir_all;
+    use tokio::time::sleep;
+
+    use cachalot::cachalot;
+
+    static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+    static MAX_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+    #[cachalot(root = ".tests_prefetch_store", prefetch = 1)]
+    async fn source(_key: &'static str, range: Range<u128>) -> impl Stream<Item = u128> {
+        let in_flight = IN_FLIGHT.fetch_add(1, Ordering::SeqCst) + 1;
+        MAX_IN_FLIGHT.fetch_max(in_flight, Ordering::SeqCst);
+
+        sleep(Duration::from_millis(50)).await;
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+
+        stream::iter(range)
+    }
+
+    source("prefetch", 1024..2048)
+        .await
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(MAX_IN_FLIGHT.load(Ordering::SeqCst), 1);
+
+    // the call past the cached page starts while the first one is in flight
+    assert_eq!(
+        source("prefetch", 0..3000).await.collect::<Vec<_>>().await,
+        (0..3000).collect::<Vec<_>>()
+    );
+    assert_eq!(MAX_IN_FLIGHT.load(Ordering::SeqCst), 2);
+
+    remove_dir_all(".tests_prefetch_store").await.unwrap()
+}
+
+#[tokio::test]
+async fn governor_store() {
+    use std::ops::Range;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use futures::future;
+    use futures::stream::{self, Stream, StreamExt};
+
+    use tokio::fs::remove_dir_all;
+    use tokio::time::{sleep, Instant};
+
+    use cachalot::cachalot;
+
+    static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+    static MAX_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+    #[cachalot(
+        root = ".tests_governor_store",
+        max_concurrent = 1,
+        max_calls = 2,
+        window_ms = 200
+    )]
+    async fn source(_key: &'static str, range: Range<u128>) -> impl Stream<Item = u128> {
+        let in_flight = IN_FLIGHT.fetch_add(1, Ordering::SeqCst) + 1;
+        MAX_IN_FLIGHT.fetch_max(in_flight, Ordering::SeqCst);
+
+        sleep(Duration::from_millis(10)).await;
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+
+        stream::iter(range)
+    }
+
+    let start = Instant::now();
+
+    let values = future::join_all(
+        ["a", "b", "c"].map(|key| async move { source(key, 0..1024).await.count().await }),
+    )
+    .await;
+
+    assert_eq!(values, [1024; 3]);
+    // the calls for every key run one at a time, and the third waits for the window
+    assert_eq!(MAX_IN_FLIGHT.load(Ordering::SeqCst), 1);
+    assert!(start.elapsed() >= Duration::from_millis(200));
+
+    remove_dir_all(".tests_governor_store").await.unwrap()
+}
+
+#[tokio::test]
+async fn retry_store() {
+    use std::ops::Range;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+
+    use tokio::fs::remove_dir_all;
+
+    use cachalot::cachalot;
+
+    #[derive(Debug, PartialEq)]
+    pub enum MyError {
+        Transient,
+        Fatal,
+    }
+
+    fn transient(err: &MyError) -> bool {
+        *err == MyError::Transient
+    }
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    #[cachalot(
+        root = ".tests_retry_store",
+        retries = 3,
+        backoff_ms = 1,
+        retry_if = "transient"
+    )]
+    async fn source(
+        key: &'static str,
+        range: Range<u128>,
+    ) -> impl Stream<Item = Result<u128, MyError>> {
+        let call = CALLS.fetch_add(1, Ordering::SeqCst);
+
+        stream::iter(range).map(move |i| match (key, call) {
+            ("fatal", _) => Err(MyError::Fatal),
+            (_, 0 | 1) => Err(MyError::Transient),
+            _ => Ok(i),
+        })
+    }
+
+    // the two transient failures are retried
+    assert_eq!(
+        source("flaky", 0..1024)
+            .await
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap(),
+        (0..1024).collect::<Vec<_>>()
+    );
+    assert_eq!(CALLS.load(Ordering::SeqCst), 3);
+
+    assert_eq!(
+        source("fatal", 0..1024).await.try_collect::<Vec<_>>().await,
+        Err(MyError::Fatal)
+    );
+    assert_eq!(CALLS.load(Ordering::SeqCst), 4);
+
+    remove_dir_all(".tests_retry_store").await.unwrap()
+}
+
+#[tokio::test]
+async fn timeout_store() {
+    use std::ops::Range;
+    use std::time::Duration;
+
+    use futures::stream::{self, Stream, StreamExt};
+
+    use tokio::fs::remove_dir_all;
+    use tokio::time::{sleep, Instant};
+
+    use cachalot::cachalot;
+
+    #[cachalot(
+        root = ".tests_timeout_store",
+        call_timeout_ms = 200,
+        item_timeout_ms = 50
+    )]
+    async fn source(key: &'static str, range: Range<u128>) -> impl Stream<Item = u128> {
+        if key == "slow" {
+            sleep(Duration::from_secs(10)).await;
+        }
+
+        let stalls = key == "stalled";
+
+        stream::iter(range).then(move |i| async move {
+            if stalls && i == 1000 {
+                sleep(Duration::from_secs(10)).await;
+            }
+
+            i
+        })
+    }
+
+    assert_eq!(
+        source("fast", 0..2048).await.collect::<Vec<_>>().await,
+        (0..2048).collect::<Vec<_>>()
+    );
+
+    // a timed out call ends the load, without the page it was filling
+    for key in ["slow", "stalled"] {
+        let start = Instant::now();
+
+        assert_eq!(source(key, 0..2048).await.collect::<Vec<_>>().await, []);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    remove_dir_all(".tests_timeout_store").await.unwrap()
+}